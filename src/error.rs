@@ -0,0 +1,120 @@
+use std::{fmt, io};
+
+/// A specialized [`Result`](std::result::Result) type for this crate's operations.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The error type returned by every fallible operation in this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying connection failed, e.g. it could not be opened, read from or written to.
+    Transport(io::Error),
+
+    /// A line received from the server could not be parsed.
+    Parse(ParseError),
+
+    /// The server rejected the login credentials. Contains the server's explanation.
+    Authentication(String),
+
+    /// The server rejected one or more of the requested capabilities.
+    CapabilityRejected(Vec<String>),
+
+    /// The server closed the connection on its own initiative. Contains the server's reason.
+    Disconnected(String),
+
+    /// Neither a `PONG` nor any other traffic was received within the keepalive timeout.
+    Timeout,
+
+    /// The server did not complete the connection handshake within the registration timeout.
+    RegistrationTimeout,
+
+    /// A message was to be sent in a read-only anonymous session.
+    Anonymous,
+
+    /// An argument passed to the API was invalid, e.g. an empty or overlong chat message.
+    InvalidInput(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(e) => write!(f, "transport error: {}", e),
+            Self::Parse(e) => write!(f, "{}", e),
+            Self::Authentication(reason) => write!(f, "authentication failed: {}", reason),
+            Self::CapabilityRejected(capabilities) => {
+                write!(f, "capabilities rejected: {}", capabilities.join(" "))
+            }
+            Self::Disconnected(reason) => write!(f, "disconnected by server: {}", reason),
+            Self::Timeout => write!(f, "connection timed out: no response to PING"),
+            Self::RegistrationTimeout => {
+                write!(f, "connection timed out: no response to registration")
+            }
+            Self::Anonymous => write!(f, "cannot send messages in an anonymous session"),
+            Self::InvalidInput(reason) => write!(f, "invalid input: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(e) => Some(e),
+            Self::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Returns `true` if the server refused the configuration itself, i.e. the login credentials
+    /// or a required capability, so that connecting again cannot succeed.
+    pub(crate) fn is_permanent(&self) -> bool {
+        matches!(self, Self::Authentication(_) | Self::CapabilityRejected(_))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Transport(e)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+/// Describes a line received from the server which could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The offending line, without the trailing CRLF.
+    pub raw: String,
+
+    /// The byte offset into `raw` at which parsing failed.
+    pub offset: usize,
+
+    /// A description of what went wrong.
+    pub reason: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(raw: &str, offset: usize, reason: impl Into<String>) -> Self {
+        Self {
+            raw: raw.trim_end_matches(['\r', '\n']).to_string(),
+            offset,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "parse error at byte {}: {} (in {:?})",
+            self.offset, self.reason, self.raw
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
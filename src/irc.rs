@@ -1,12 +1,70 @@
 use std::{
-    io::{self, BufRead, BufReader, Write},
-    net::{TcpStream, ToSocketAddrs},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt,
+    io::{self, BufRead, BufReader},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
 };
 
+use crate::{Error, ParseError, Result};
+
+mod badges;
+mod cheer;
+mod color;
+mod handover;
+mod membership;
+mod moderation;
+mod notice;
+mod outgoing;
+mod privmsg;
+mod reconnect;
+mod registration;
+mod reply;
+mod roomstate;
+mod tags;
+#[cfg(feature = "tls")]
+mod tls;
+mod transport;
+mod usernotice;
+mod userstate;
+
+pub use badges::Badge;
+pub use cheer::Cheermote;
+pub use color::Rgb;
+use handover::{Draining, Handover, RecentIds, Replacement};
+use membership::Membership;
+pub use moderation::{ClearChat, ClearChatAction, ClearMsg};
+pub use notice::{Notice, NoticeKind};
+pub use outgoing::{LongMessagePolicy, MAX_MESSAGE_LEN};
+use outgoing::{OutgoingMessage, RateLimiter};
+pub use privmsg::{Emote, PrivMsg, Segment};
+pub use reconnect::ReconnectPolicy;
+use registration::Handshake;
+pub use reply::ReplyContext;
+pub use roomstate::{RoomState, RoomStateUpdate};
+pub use tags::Tags;
+#[cfg(feature = "tls")]
+pub use tls::TlsConfig;
+use transport::Transport;
+pub use usernotice::{SubPlan, UserNotice, UserNoticeKind};
+pub use userstate::{GlobalUserState, UserState};
+
+/// The default amount of idle time after which a client `PING` is sent to the server.
+const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The default amount of time to wait for a `PONG` (or any other traffic) after a client `PING`
+/// before the connection is declared dead.
+const DEFAULT_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The default amount of time the server has to answer the connection handshake.
+const DEFAULT_REGISTRATION_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// A builder which is used to configure and initialize an [`Irc`] connection.
 ///
 /// ## Example
-/// ```rust,norun
+/// ```rust,no_run
+/// # use consolation::irc::IrcBuilder;
+/// # fn main() -> consolation::Result<()> {
 /// let mut irc = IrcBuilder::default()
 ///     .with_nickname("nickname")
 ///     .with_password("my_password")
@@ -19,20 +77,74 @@ use std::{
 /// while let Some(message) = irc.receive()? {
 ///     println!("message received: {:?}", message);
 /// }
+/// # Ok(())
+/// # }
 /// ```
-#[derive(Debug, Clone, Default)]
+#[derive(Clone, Default)]
 pub struct IrcBuilder {
     password: Option<String>,
     nickname: Option<String>,
     capabilities: Vec<String>,
+    required_capabilities: Vec<String>,
+    capability_negotiation: bool,
+    registration_timeout: Option<Duration>,
+    ping_interval: Option<Duration>,
+    keepalive_timeout: Option<Duration>,
+    long_message_policy: LongMessagePolicy,
+    lenient: bool,
+    member_tracking: bool,
+    reconnect: Option<ReconnectPolicy>,
+    anonymous: bool,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+}
+
+impl fmt::Debug for IrcBuilder {
+    /// Formats the builder with the password redacted, so that it does not end up in logs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("IrcBuilder");
+        debug
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("nickname", &self.nickname)
+            .field("capabilities", &self.capabilities)
+            .field("required_capabilities", &self.required_capabilities)
+            .field("capability_negotiation", &self.capability_negotiation)
+            .field("registration_timeout", &self.registration_timeout)
+            .field("ping_interval", &self.ping_interval)
+            .field("keepalive_timeout", &self.keepalive_timeout)
+            .field("long_message_policy", &self.long_message_policy)
+            .field("lenient", &self.lenient)
+            .field("member_tracking", &self.member_tracking)
+            .field("reconnect", &self.reconnect)
+            .field("anonymous", &self.anonymous);
+        #[cfg(feature = "tls")]
+        debug.field("tls", &self.tls);
+
+        debug.finish()
+    }
 }
 
 impl IrcBuilder {
+    /// Creates a builder for a read-only anonymous session, which Twitch allows without any
+    /// credentials.
+    ///
+    /// A random `justinfanNNNNN` nickname is used and no `PASS` message is sent. Channels can be
+    /// joined and read as usual, but [`Irc::privmsg`] fails with [`Error::Anonymous`].
+    pub fn anonymous() -> Self {
+        Self {
+            nickname: Some(format!("justinfan{}", reconnect::random_u64() % 100_000)),
+            anonymous: true,
+            ..Self::default()
+        }
+    }
+
     /// Specifies a password to authenticate with.
     ///
-    /// If specified, a `PASS` message will be sent when [`IrcBuilder::connect`] is called.
+    /// If specified, a `PASS` message will be sent when [`IrcBuilder::connect`] is called. This
+    /// turns a builder created with [`IrcBuilder::anonymous`] into a regular login.
     pub fn with_password(mut self, password: impl Into<String>) -> Self {
         self.password = Some(password.into());
+        self.anonymous = false;
 
         self
     }
@@ -50,33 +162,210 @@ impl IrcBuilder {
     /// be called multiple times to request multiple capabilities.
     ///
     /// If one or more capability is added to the builder, a `CAP REQ` message will be sent when
-    /// [`IrcBuilder::connect`] is called.
+    /// [`IrcBuilder::connect`] is called, which then waits for the server's `CAP ACK` or `CAP NAK`.
+    /// The acknowledged capabilities can be queried with [`Irc::capabilities`].
     pub fn with_capability(mut self, capability_name: impl Into<String>) -> Self {
         self.capabilities.push(capability_name.into());
 
         self
     }
 
+    /// Appends a capability which will be requested like with [`IrcBuilder::with_capability`],
+    /// but without which [`IrcBuilder::connect`] fails with [`Error::CapabilityRejected`].
+    pub fn with_required_capability(mut self, capability_name: impl Into<String>) -> Self {
+        let capability_name = capability_name.into();
+        self.required_capabilities.push(capability_name.clone());
+        self.capabilities.push(capability_name);
+
+        self
+    }
+
+    /// Enables or disables full IRCv3 capability negotiation, as needed by generic IRCv3 servers.
+    ///
+    /// When enabled, supported capabilities are first discovered with `CAP LS 302`, only those
+    /// the server advertises are requested, and negotiation is closed with `CAP END`. Twitch does
+    /// not need this.
+    ///
+    /// Disabled by default.
+    pub fn with_capability_negotiation(mut self, capability_negotiation: bool) -> Self {
+        self.capability_negotiation = capability_negotiation;
+
+        self
+    }
+
+    /// Specifies how long the server has to answer the connection handshake before
+    /// [`IrcBuilder::connect`] fails with [`Error::RegistrationTimeout`]. Opening the connection
+    /// and the TLS handshake are bounded by the same duration.
+    ///
+    /// Defaults to 10 seconds.
+    pub fn with_registration_timeout(mut self, registration_timeout: Duration) -> Self {
+        self.registration_timeout = Some(registration_timeout);
+
+        self
+    }
+
+    /// Specifies how long the connection may stay idle before a `PING` is sent to the server to
+    /// check that it is still alive.
+    ///
+    /// Defaults to 60 seconds.
+    pub fn with_ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.ping_interval = Some(ping_interval);
+
+        self
+    }
+
+    /// Specifies how long to wait for a `PONG` (or any other traffic) after a client `PING` has
+    /// been sent before the connection is declared dead.
+    ///
+    /// Defaults to 20 seconds.
+    pub fn with_keepalive_timeout(mut self, keepalive_timeout: Duration) -> Self {
+        self.keepalive_timeout = Some(keepalive_timeout);
+
+        self
+    }
+
+    /// Specifies what [`Irc::privmsg`] does with messages longer than [`MAX_MESSAGE_LEN`]
+    /// characters.
+    ///
+    /// Defaults to [`LongMessagePolicy::Reject`].
+    pub fn with_long_message_policy(mut self, long_message_policy: LongMessagePolicy) -> Self {
+        self.long_message_policy = long_message_policy;
+
+        self
+    }
+
+    /// Enables or disables lenient parsing.
+    ///
+    /// In lenient mode, lines which cannot be parsed are reported by [`Irc::receive`] as
+    /// [`Message::Malformed`] events instead of errors, so the connection keeps running, and
+    /// invalid UTF-8 is replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// Disabled by default.
+    pub fn with_lenient_parsing(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+
+        self
+    }
+
+    /// Enables or disables keeping track of the users in each joined channel, which can then be
+    /// queried with [`Irc::members`].
+    ///
+    /// Users are only reported by the server in a standard IRC session, or if the
+    /// `twitch.tv/membership` capability was requested. Twitch only lists existing users on join
+    /// in channels with fewer than 1000 of them, and reports joins and parts in batches every few
+    /// seconds.
+    ///
+    /// Disabled by default.
+    pub fn with_member_tracking(mut self, member_tracking: bool) -> Self {
+        self.member_tracking = member_tracking;
+
+        self
+    }
+
+    /// Enables automatic reconnection when the connection drops.
+    ///
+    /// When enabled, [`Irc::receive`] reports a lost connection as a [`Message::Disconnected`]
+    /// event instead of an error or end of stream. The following call then reconnects according
+    /// to `policy`, requests the same capabilities, authenticates again, rejoins every channel
+    /// joined through the handle and returns [`Message::Reconnected`].
+    ///
+    /// Disabled by default.
+    pub fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+
+        self
+    }
+
+    /// Connects over TLS using the given settings instead of plaintext TCP.
+    ///
+    /// Twitch accepts TLS connections on port 6697.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+
+        self
+    }
+
     /// Attempts to connect to the IRC server, returning an [`Irc`] connection handle on success.
     ///
     /// If credentials were previously added to the builder, authorization commands will be sent
     /// when this function is called. Likewise, if capabilities were added, they will be requested
-    /// during this function call as well.
+    /// during this function call as well, and the server's answer is awaited.
+    ///
+    /// This function blocks until the server confirms the registration with `001 RPL_WELCOME`
+    /// (see [`Irc::server_info`]), failing with [`Error::Authentication`] if the login is rejected
+    /// or [`Error::RegistrationTimeout`] if the registration timeout elapses first.
     ///
     /// Do not include `irc://` in the `addr` parameter.
-    pub fn connect(self, addr: impl ToSocketAddrs) -> io::Result<Irc> {
-        let conn = TcpStream::connect(addr)?;
-        let reader = BufReader::new(conn.try_clone()?);
+    pub fn connect(self, addr: impl ToSocketAddrs) -> Result<Irc> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        let mut conn = BufReader::new(self.open_transport(&addrs)?);
+
+        let mut handshake = Handshake::new(&mut conn, self.registration_timeout());
+        let capabilities = handshake.negotiate(&self)?;
+        let server_info = handshake.await_welcome()?;
+        let backlog = handshake.into_backlog().into();
+
+        let keepalive = Keepalive::new(
+            self.ping_interval.unwrap_or(DEFAULT_PING_INTERVAL),
+            self.keepalive_timeout.unwrap_or(DEFAULT_KEEPALIVE_TIMEOUT),
+        );
+
+        Ok(Irc {
+            conn,
+            line: Vec::new(),
+            keepalive,
+            channels: BTreeSet::new(),
+            room_states: BTreeMap::new(),
+            user_states: BTreeMap::new(),
+            global_user_state: None,
+            outgoing: VecDeque::new(),
+            rate_limiter: RateLimiter::default(),
+            addrs,
+            membership: Membership::new(self.member_tracking),
+            config: self,
+            disconnected: false,
+            handover: None,
+            draining: None,
+            recent_ids: RecentIds::default(),
+            capabilities,
+            server_info,
+            backlog,
+        })
+    }
 
-        let mut irc = Irc { conn, reader };
-        if self.capabilities.len() > 0 {
-            irc.request_capabilities(&self.capabilities)?;
+    /// Returns the configured registration timeout, or the default.
+    fn registration_timeout(&self) -> Duration {
+        self.registration_timeout
+            .unwrap_or(DEFAULT_REGISTRATION_TIMEOUT)
+    }
+
+    /// Opens a TCP connection to the first reachable address, wrapping it in TLS if configured.
+    ///
+    /// Connecting to each address and the TLS handshake are each bounded by the registration
+    /// timeout, which is also kept as the socket's write timeout.
+    fn open_transport(&self, addrs: &[SocketAddr]) -> Result<Transport> {
+        let timeout = self.registration_timeout();
+        let mut result = Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any addresses",
+        ));
+        for addr in addrs {
+            result = TcpStream::connect_timeout(addr, timeout);
+            if result.is_ok() {
+                break;
+            }
         }
-        if self.password.is_some() || self.nickname.is_some() {
-            irc.authenticate(self.password, self.nickname)?;
+        let conn = result?;
+        conn.set_read_timeout(Some(timeout))?;
+        conn.set_write_timeout(Some(timeout))?;
+
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return tls.wrap(conn);
         }
 
-        Ok(irc)
+        Ok(Transport::Plain(conn))
     }
 }
 
@@ -85,72 +374,778 @@ impl IrcBuilder {
 /// In order to connect to an IRC server (and construct an [`Irc`]), use an [`IrcBuilder`].
 #[derive(Debug)]
 pub struct Irc {
-    conn: TcpStream,
-    reader: BufReader<TcpStream>,
+    conn: BufReader<Transport>,
+
+    /// A partially received line, kept across read timeouts.
+    line: Vec<u8>,
+
+    keepalive: Keepalive,
+
+    /// The channels currently joined through this handle, normalized by [`channel_name_normalized`].
+    channels: BTreeSet<String>,
+
+    /// The chat settings of each channel, keyed by normalized channel name.
+    room_states: BTreeMap<String, RoomState>,
+
+    /// The logged-in user's details in each channel, keyed by normalized channel name.
+    user_states: BTreeMap<String, UserState>,
+
+    global_user_state: Option<GlobalUserState>,
+
+    /// Chat messages waiting to be sent once the rate limit allows it.
+    outgoing: VecDeque<OutgoingMessage>,
+
+    rate_limiter: RateLimiter,
+
+    /// The resolved addresses of the server, kept for reconnecting.
+    addrs: Vec<SocketAddr>,
+
+    /// The builder this handle was created from, kept for reconnecting.
+    config: IrcBuilder,
+
+    /// Whether the connection was lost and [`Irc::receive`] must reconnect before reading again.
+    disconnected: bool,
+
+    /// The replacement connection being prepared after the server sent `RECONNECT`.
+    handover: Option<Handover>,
+
+    /// The connection replaced by the last handover, read until the server closes it.
+    draining: Option<Draining>,
+
+    /// The IDs of recently received messages, used to drop duplicates.
+    recent_ids: RecentIds,
+
+    /// The `NAMES` replies being received, and the users in each channel if tracked.
+    membership: Membership,
+
+    /// The capabilities acknowledged by the server.
+    capabilities: BTreeSet<String>,
+
+    server_info: ServerInfo,
+
+    /// Lines received during the connection handshake which have not been delivered yet.
+    backlog: VecDeque<Vec<u8>>,
 }
 
 impl Irc {
-    /// Requests a list of capabilities from the server.
-    fn request_capabilities(&mut self, capabilities: &[String]) -> io::Result<()> {
-        let capabilities_str = capabilities.join(" ");
-        writeln!(self.conn, "CAP REQ :{}", capabilities_str)?;
+    /// Writes a single line to the server, appending the trailing CRLF.
+    fn send_line(&mut self, line: &str) -> io::Result<()> {
+        self.conn.get_mut().send_line(line)
+    }
 
-        Ok(())
+    /// Returns an iterator over the capabilities acknowledged by the server.
+    pub fn capabilities(&self) -> impl Iterator<Item = &str> {
+        self.capabilities.iter().map(String::as_str)
     }
 
-    /// Authenticates the user with an optional password and nickname.
-    fn authenticate(
-        &mut self,
-        password: Option<String>,
-        nickname: Option<String>,
-    ) -> io::Result<()> {
-        if let Some(password) = password {
-            writeln!(self.conn, "PASS {}", password)?;
-        }
+    /// Returns the details the server sent when confirming the registration.
+    pub fn server_info(&self) -> &ServerInfo {
+        &self.server_info
+    }
 
-        if let Some(nickname) = nickname {
-            writeln!(self.conn, "NICK {}", nickname)?;
-        }
+    /// Returns `true` if this is a read-only anonymous session (see [`IrcBuilder::anonymous`]).
+    pub fn is_anonymous(&self) -> bool {
+        self.config.anonymous
+    }
 
-        Ok(())
+    /// Returns `true` if the server acknowledged the given capability.
+    pub fn has_capability(&self, capability_name: &str) -> bool {
+        self.capabilities.contains(capability_name)
     }
 
     /// Blocks the current thread until the next parseable message is received from the IRC server.
     ///
-    /// A value of `Ok(None)` will be returned if and only if the connection is closed.
-    pub fn receive(&mut self) -> io::Result<Option<Message>> {
+    /// Every message is returned, falling back to [`Message::Unknown`] for commands without a typed
+    /// variant, except for `PING`, `PONG` and `RECONNECT`, which are handled internally.
+    ///
+    /// When Twitch sends `RECONNECT` ahead of server maintenance, a replacement connection is
+    /// opened and registered in the background, every channel is rejoined on it, and reading
+    /// switches over to it once it is ready. The previous connection keeps being read until the
+    /// server closes it, so nothing sent on it is lost. Messages carrying an `id` tag which were
+    /// already received are dropped, so nothing is doubled while both connections are open.
+    ///
+    /// Server `PING`s are answered automatically, and a client `PING` is sent whenever the
+    /// connection has been idle for the configured ping interval. If neither a `PONG` nor any
+    /// other traffic arrives within the keepalive timeout after that, [`Error::Timeout`] is
+    /// returned.
+    ///
+    /// Queued chat messages (see [`Irc::privmsg`]) are sent while waiting, as soon as the rate
+    /// limit allows.
+    ///
+    /// A value of `Ok(None)` will be returned if and only if the connection is closed, unless
+    /// reconnection is enabled with [`IrcBuilder::with_reconnect`]: in that case, a lost
+    /// connection is reported as [`Message::Disconnected`] and the next call reconnects. If the
+    /// server then rejects the login or a required capability, [`Error::Authentication`] or
+    /// [`Error::CapabilityRejected`] is returned without further attempts.
+    pub fn receive(&mut self) -> Result<Option<Message>> {
+        if self.disconnected {
+            let attempts = self.reconnect()?;
+            self.disconnected = false;
+
+            return Ok(Some(Message::Reconnected { attempts }));
+        }
+
+        let error = match self.read_message() {
+            Ok(None) => None,
+            Err(e @ (Error::Transport(_) | Error::Disconnected(_) | Error::Timeout)) => Some(e),
+            result => return result,
+        };
+        if self.config.reconnect.is_none() {
+            return match error {
+                Some(e) => Err(e),
+                None => Ok(None),
+            };
+        }
+
+        let reason = error.map_or_else(|| "connection closed".to_string(), |e| e.to_string());
+        self.disconnected = true;
+        Ok(Some(Message::Disconnected { reason }))
+    }
+
+    /// Reads the next message from the current connection, without any reconnection handling.
+    fn read_message(&mut self) -> Result<Option<Message>> {
         loop {
-            let mut buf = String::new();
-            let n = self.reader.read_line(&mut buf)?;
-            if n == 0 {
-                return Ok(None);
+            let bytes = if let Some(bytes) = self.backlog.pop_front() {
+                bytes
             } else {
-                let raw_msg = IrcMessageRaw::parse(&buf)?;
-                let message = Message::from_raw_msg(raw_msg)?;
+                if self.handover.as_ref().is_some_and(Handover::is_finished) {
+                    self.finish_handover()?;
+                }
+                if self.poll_draining() {
+                    continue;
+                }
+
+                let next_send_at = self.flush_outgoing()?;
+
+                let now = Instant::now();
+                let deadline = self.keepalive.deadline();
+                if now >= deadline {
+                    if self.keepalive.ping_sent_at.is_some() {
+                        return Err(Error::Timeout);
+                    }
+
+                    self.ping()?;
+                    continue;
+                }
+
+                let mut wake_at = next_send_at.map_or(deadline, |send_at| send_at.min(deadline));
+                if self.handover.is_some() || self.draining.is_some() {
+                    wake_at = wake_at.min(now + handover::POLL_INTERVAL);
+                }
+                if wake_at <= now {
+                    continue;
+                }
+
+                self.conn
+                    .get_ref()
+                    .tcp()
+                    .set_read_timeout(Some(wake_at - now))?;
+                match self.conn.read_until(b'\n', &mut self.line) {
+                    Ok(0) if self.handover.is_some() && self.finish_handover()? => continue,
+                    Ok(0) => return Ok(None),
+                    Ok(_) => {}
+                    Err(e)
+                        if e.kind() == io::ErrorKind::WouldBlock
+                            || e.kind() == io::ErrorKind::TimedOut =>
+                    {
+                        continue
+                    }
+                    Err(_) if self.handover.is_some() && self.finish_handover()? => continue,
+                    Err(e) => return Err(e.into()),
+                }
+
+                self.keepalive.record_activity();
+                let bytes = std::mem::take(&mut self.line);
+
+                // Lines still arriving on the replaced connection were sent earlier.
+                if self.poll_draining() {
+                    self.backlog.push_back(bytes);
+                    continue;
+                }
+
+                bytes
+            };
+
+            let line = if self.config.lenient {
+                String::from_utf8_lossy(&bytes).into_owned()
+            } else {
+                String::from_utf8(bytes).map_err(|e| {
+                    ParseError::new(
+                        &String::from_utf8_lossy(e.as_bytes()),
+                        e.utf8_error().valid_up_to(),
+                        "invalid UTF-8",
+                    )
+                })?
+            };
+            if line.trim_matches([' ', '\r', '\n']).is_empty() {
+                continue;
+            }
+
+            let raw_msg = match IrcMessageRaw::parse(&line) {
+                Ok(raw_msg) => raw_msg,
+                Err(error) => return self.parse_failure(error),
+            };
+            match raw_msg.command_name.as_str() {
+                "PING" => {
+                    self.pong(&raw_msg.command_params)?;
+                    continue;
+                }
+                "PONG" => continue,
+                "RECONNECT" => {
+                    if self.handover.is_none() {
+                        self.handover = Some(Handover::start(
+                            self.config.clone(),
+                            self.addrs.clone(),
+                            self.channels.iter().cloned().collect(),
+                        ));
+                    }
+                    continue;
+                }
+                "ERROR" => {
+                    let reason = raw_msg.command_params.last().cloned().unwrap_or_default();
+                    return Err(Error::Disconnected(reason));
+                }
+                // A `NAMES` reply may span several `353` lines, so they are collected and
+                // delivered together on `366`. Lines without the expected parameters are
+                // delivered as `Message::Unknown` instead.
+                "353" => {
+                    if let [_, _, channel, names] = raw_msg.command_params.as_slice() {
+                        self.membership
+                            .push_names(&channel_name_normalized(channel), names);
+                        continue;
+                    }
+                }
+                "366" => {
+                    if let Some(channel) = raw_msg.command_params.get(1) {
+                        let names = self
+                            .membership
+                            .take_names(&channel_name_normalized(channel));
+                        let message = Message::Names {
+                            channel: channel.clone(),
+                            names,
+                        };
+                        self.track_state(&message);
+
+                        return Ok(Some(message));
+                    }
+                }
+                "NOTICE" if is_login_failure(&raw_msg) => {
+                    let reason = raw_msg.command_params.last().cloned().unwrap_or_default();
+                    return Err(Error::Authentication(reason));
+                }
+                _ => {}
+            }
+
+            if let Some(id) = raw_msg.tags.get("id") {
+                if !self.recent_ids.insert(id) {
+                    continue;
+                }
+            }
 
-                if let Some(message) = message {
-                    return Ok(Some(message));
+            let message = match Message::from_raw_msg(raw_msg, &line) {
+                Ok(message) => message,
+                Err(error) => return self.parse_failure(error),
+            };
+            self.track_state(&message);
+
+            return Ok(Some(message));
+        }
+    }
+
+    /// Moves the lines available on the connection replaced by a handover, if any, to the
+    /// backlog, returning `true` if there were some.
+    fn poll_draining(&mut self) -> bool {
+        let Some(draining) = &mut self.draining else {
+            return false;
+        };
+        if !draining.poll(&mut self.backlog) {
+            self.draining = None;
+        }
+
+        !self.backlog.is_empty()
+    }
+
+    /// Updates the channel and user state kept on this handle from a received message.
+    fn track_state(&mut self, message: &Message) {
+        match message {
+            Message::RoomState(update) => {
+                self.room_states
+                    .entry(channel_name_normalized(&update.channel))
+                    .or_default()
+                    .apply(update);
+            }
+            Message::UserState(state) => {
+                let channel = channel_name_normalized(&state.channel);
+                self.rate_limiter
+                    .set_moderator(&channel, state.is_privileged());
+                self.user_states.insert(channel, state.clone());
+            }
+            Message::GlobalUserState(state) => self.global_user_state = Some(state.clone()),
+            Message::Join { channel, user } => {
+                let channel = channel_name_normalized(channel);
+                if user.eq_ignore_ascii_case(&self.server_info.nickname) {
+                    self.membership.track_channel(&channel);
                 }
+                self.membership.join(&channel, user);
+            }
+            Message::Part { channel, user } => {
+                let channel = channel_name_normalized(channel);
+                if user.eq_ignore_ascii_case(&self.server_info.nickname) {
+                    self.membership.forget_channel(&channel);
+                } else {
+                    self.membership.part(&channel, user);
+                }
+            }
+            Message::Names { channel, names } => {
+                self.membership
+                    .names(&channel_name_normalized(channel), names);
+            }
+            _ => {}
+        }
+    }
+
+    /// Switches over to the replacement connection once the handover thread is done, joining or
+    /// parting any channels changed in the meantime. The previous connection is drained (see
+    /// [`Draining`]).
+    ///
+    /// Blocks until the thread finishes, then returns `false` if it failed, in which case the
+    /// current connection is kept, unless the login or a required capability was rejected.
+    fn finish_handover(&mut self) -> Result<bool> {
+        let Some(handover) = self.handover.take() else {
+            return Ok(false);
+        };
+        let Replacement {
+            conn,
+            capabilities,
+            server_info,
+            channels: joined,
+        } = match handover.finish() {
+            Ok(replacement) => replacement,
+            Err(e) if e.is_permanent() => return Err(e),
+            Err(_) => return Ok(false),
+        };
+
+        let previous = std::mem::replace(&mut self.conn, conn);
+        self.draining = Some(Draining::new(previous, std::mem::take(&mut self.line)));
+        self.capabilities = capabilities;
+        self.server_info = server_info;
+        self.keepalive.record_activity();
+
+        let to_join: Vec<&str> = self
+            .channels
+            .iter()
+            .filter(|channel| !joined.contains(channel))
+            .map(String::as_str)
+            .collect();
+        if !to_join.is_empty() {
+            let line = format!("JOIN {}", to_join.join(","));
+            self.send_line(&line)?;
+        }
+        for channel in joined {
+            if !self.channels.contains(&channel) {
+                self.send_line(&format!("PART {}", channel))?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Reconnects to the server according to the reconnect policy, returning the number of
+    /// attempts it took.
+    ///
+    /// A rejected login or required capability is returned right away instead of being retried.
+    fn reconnect(&mut self) -> Result<u32> {
+        let Some(policy) = self.config.reconnect.clone() else {
+            return Err(Error::Disconnected("reconnection is disabled".into()));
+        };
+
+        let mut attempts = 0;
+        loop {
+            std::thread::sleep(policy.delay(attempts));
+            attempts += 1;
+
+            match self.reopen() {
+                Ok(()) => return Ok(attempts),
+                Err(e) if e.is_permanent() || policy.is_exhausted(attempts) => return Err(e),
+                Err(_) => continue,
             }
         }
     }
 
+    /// Replaces the current connection with a fresh one, registering again and rejoining every
+    /// channel.
+    fn reopen(&mut self) -> Result<()> {
+        let mut conn = BufReader::new(self.config.open_transport(&self.addrs)?);
+        let mut handshake = Handshake::new(&mut conn, self.config.registration_timeout());
+        self.capabilities = handshake.negotiate(&self.config)?;
+        self.server_info = handshake.await_welcome()?;
+        self.backlog = handshake.into_backlog().into();
+
+        self.conn = conn;
+        self.handover = None;
+        self.draining = None;
+        self.line.clear();
+        self.membership.clear();
+        self.keepalive.record_activity();
+
+        if !self.channels.is_empty() {
+            let channels: Vec<&str> = self.channels.iter().map(String::as_str).collect();
+            let line = format!("JOIN {}", channels.join(","));
+            self.send_line(&line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports a line which could not be parsed as a [`Message::Malformed`] event in lenient mode,
+    /// or as an error otherwise.
+    fn parse_failure(&self, error: ParseError) -> Result<Option<Message>> {
+        if self.config.lenient {
+            Ok(Some(Message::Malformed {
+                raw: error.raw.clone(),
+                error,
+            }))
+        } else {
+            Err(error.into())
+        }
+    }
+
+    /// Sends a client `PING` to the server and starts waiting for the matching `PONG`.
+    fn ping(&mut self) -> Result<()> {
+        self.send_line("PING :consolation")?;
+        self.keepalive.ping_sent_at = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// Answers a server `PING`, echoing back its parameters.
+    fn pong(&mut self, params: &[String]) -> Result<()> {
+        match params.last() {
+            Some(token) => self.send_line(&format!("PONG :{}", token))?,
+            None => self.send_line("PONG")?,
+        }
+
+        Ok(())
+    }
+
     /// Joins an IRC channel.
     ///
-    /// Do not include a leading `#` in `channel_name`.
-    pub fn join(&mut self, channel_name: impl Into<String>) -> io::Result<()> {
-        writeln!(self.conn, "JOIN #{}", channel_name.into())
+    /// The leading `#` in `channel_name` is optional. Fails with [`Error::InvalidInput`] if the
+    /// name is empty or contains whitespace, `,` or NUL characters.
+    pub fn join(&mut self, channel_name: impl Into<String>) -> Result<()> {
+        self.join_all([channel_name])
+    }
+
+    /// Joins several IRC channels at once using a single `JOIN` command.
+    ///
+    /// The leading `#` in each channel name is optional. Nothing is sent if the list is empty, or
+    /// if any name is invalid (see [`Irc::join`]).
+    pub fn join_all<I, S>(&mut self, channel_names: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let channel_names: Vec<String> = channel_names
+            .into_iter()
+            .map(|name| channel_name_checked(&name.into()))
+            .collect::<Result<_>>()?;
+        if channel_names.is_empty() {
+            return Ok(());
+        }
+
+        self.send_line(&format!("JOIN {}", channel_names.join(",")))?;
+        for channel in &channel_names {
+            self.membership.track_channel(channel);
+        }
+        self.channels.extend(channel_names);
+
+        Ok(())
+    }
+
+    /// Leaves an IRC channel.
+    ///
+    /// The leading `#` in `channel_name` is optional. Fails with [`Error::InvalidInput`] if the
+    /// name is invalid (see [`Irc::join`]).
+    pub fn part(&mut self, channel_name: impl Into<String>) -> Result<()> {
+        let channel_name = channel_name_checked(&channel_name.into())?;
+
+        self.send_line(&format!("PART {}", channel_name))?;
+        self.channels.remove(&channel_name);
+        self.membership.forget_channel(&channel_name);
+        self.room_states.remove(&channel_name);
+        self.user_states.remove(&channel_name);
+
+        Ok(())
+    }
+
+    /// Queues a chat message to be sent to a channel.
+    ///
+    /// Messages are sent in order, as soon as Twitch's rate limits allow: 20 messages per 30
+    /// seconds, and at most one message per second to the same channel. In channels where the
+    /// logged-in user is a moderator, a VIP or the broadcaster, as reported by the server's
+    /// `USERSTATE` (see [`Irc::user_state`]) or marked with [`Irc::set_moderator`], the limit is
+    /// 100 messages per 30 seconds without per-channel spacing. Messages that can be sent
+    /// immediately are written before this function returns; the rest are sent by later calls to
+    /// [`Irc::receive`] or [`Irc::flush`].
+    ///
+    /// CR and LF characters in `text` are replaced with spaces. Messages longer than
+    /// [`MAX_MESSAGE_LEN`] characters are rejected or split according to the builder's
+    /// [`LongMessagePolicy`]. The leading `#` in `channel_name` is optional; invalid names (see
+    /// [`Irc::join`]) are rejected with [`Error::InvalidInput`].
+    ///
+    /// Fails with [`Error::Anonymous`] in an anonymous session.
+    pub fn privmsg(&mut self, channel_name: &str, text: &str) -> Result<()> {
+        self.queue_message(channel_name, text, None)
+    }
+
+    /// Queues a chat message replying to another message, shown as such by Twitch.
+    ///
+    /// `parent_msg_id` is the `id` tag of the message being replied to (see [`PrivMsg::id`]).
+    /// Twitch adds the `@login` mention of its sender on its own. The message is otherwise sent
+    /// like with [`Irc::privmsg`]; if it is split, every part is sent as a reply.
+    pub fn reply(&mut self, channel_name: &str, parent_msg_id: &str, text: &str) -> Result<()> {
+        if parent_msg_id.is_empty() {
+            return Err(Error::InvalidInput(
+                "cannot reply without a message ID".into(),
+            ));
+        }
+
+        self.queue_message(channel_name, text, Some(parent_msg_id))
+    }
+
+    /// Queues a chat message as described in [`Irc::privmsg`], as a reply to `reply_parent_msg_id`
+    /// if given.
+    fn queue_message(
+        &mut self,
+        channel_name: &str,
+        text: &str,
+        reply_parent_msg_id: Option<&str>,
+    ) -> Result<()> {
+        if self.is_anonymous() {
+            return Err(Error::Anonymous);
+        }
+
+        let channel = channel_name_checked(channel_name)?;
+        let text = outgoing::sanitize(text);
+        if text.trim().is_empty() {
+            return Err(Error::InvalidInput("cannot send an empty message".into()));
+        }
+
+        let texts = if text.chars().count() <= MAX_MESSAGE_LEN {
+            vec![text]
+        } else {
+            match self.config.long_message_policy {
+                LongMessagePolicy::Reject => {
+                    return Err(Error::InvalidInput(format!(
+                        "message exceeds {} characters",
+                        MAX_MESSAGE_LEN
+                    )));
+                }
+                LongMessagePolicy::Split => outgoing::split(&text, MAX_MESSAGE_LEN),
+            }
+        };
+
+        self.outgoing
+            .extend(texts.into_iter().map(|text| OutgoingMessage {
+                channel: channel.clone(),
+                text,
+                reply_parent_msg_id: reply_parent_msg_id.map(str::to_string),
+            }));
+        self.flush_outgoing()?;
+
+        Ok(())
+    }
+
+    /// Blocks the current thread until every queued chat message has been sent.
+    pub fn flush(&mut self) -> Result<()> {
+        while let Some(send_at) = self.flush_outgoing()? {
+            std::thread::sleep(send_at.saturating_duration_since(Instant::now()));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of chat messages waiting to be sent.
+    pub fn pending_messages(&self) -> usize {
+        self.outgoing.len()
+    }
+
+    /// Marks whether the logged-in user is a moderator (or a VIP or the broadcaster) in a channel,
+    /// which raises the rate limit for messages sent there.
+    ///
+    /// This is done automatically when the server sends a `USERSTATE`, which overrides the value
+    /// set here. The leading `#` in `channel_name` is optional.
+    pub fn set_moderator(&mut self, channel_name: &str, is_moderator: bool) {
+        self.rate_limiter
+            .set_moderator(&channel_name_normalized(channel_name), is_moderator);
+    }
+
+    /// Sends as many queued chat messages as the rate limit currently allows, returning the
+    /// instant at which the next one may be sent if the queue is not empty.
+    fn flush_outgoing(&mut self) -> Result<Option<Instant>> {
+        while let Some(message) = self.outgoing.front() {
+            let now = Instant::now();
+            let send_at = self.rate_limiter.next_send_at(&message.channel, now);
+            if send_at > now {
+                return Ok(Some(send_at));
+            }
+
+            let mut line = format!("PRIVMSG {} :{}", message.channel, message.text);
+            if let Some(parent_msg_id) = &message.reply_parent_msg_id {
+                line.insert_str(
+                    0,
+                    &format!("@reply-parent-msg-id={} ", tags::escape(parent_msg_id)),
+                );
+            }
+            self.send_line(&line)?;
+            if let Some(message) = self.outgoing.pop_front() {
+                self.rate_limiter.record(&message.channel, now);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the chat settings of a channel, as last reported by the server, or `None` if none
+    /// were received yet.
+    ///
+    /// Settings are only sent by the server if the `twitch.tv/commands` and `twitch.tv/tags`
+    /// capabilities were requested. The leading `#` in `channel_name` is optional.
+    pub fn room_state(&self, channel_name: &str) -> Option<&RoomState> {
+        self.room_states.get(&channel_name_normalized(channel_name))
+    }
+
+    /// Returns the logged-in user's details in a channel, as last reported by the server, or
+    /// `None` if none were received yet.
+    ///
+    /// Details are only sent by the server if the `twitch.tv/commands` and `twitch.tv/tags`
+    /// capabilities were requested. The leading `#` in `channel_name` is optional.
+    pub fn user_state(&self, channel_name: &str) -> Option<&UserState> {
+        self.user_states.get(&channel_name_normalized(channel_name))
+    }
+
+    /// Returns the logged-in user's global details, or `None` if the server did not send them.
+    pub fn global_user_state(&self) -> Option<&GlobalUserState> {
+        self.global_user_state.as_ref()
+    }
+
+    /// Returns an iterator over the login names of the users currently in a channel, or `None` if
+    /// member tracking is disabled (see [`IrcBuilder::with_member_tracking`]) or the channel is
+    /// not joined.
+    ///
+    /// The leading `#` in `channel_name` is optional.
+    pub fn members(&self, channel_name: &str) -> Option<impl Iterator<Item = &str>> {
+        let members = self
+            .membership
+            .members(&channel_name_normalized(channel_name))?;
+
+        Some(members.iter().map(String::as_str))
+    }
+
+    /// Returns an iterator over the channels currently joined, each including the leading `#`.
+    pub fn channels(&self) -> impl Iterator<Item = &str> {
+        self.channels.iter().map(String::as_str)
     }
 }
 
-/// Represents a private IRC message sent by a user or bot and received in an IRC channel.
-#[derive(Debug, Clone)]
-pub struct PrivMsg {
-    /// The username of the message sender.
-    pub username: String,
+/// Returns `true` if `raw_msg` is the `NOTICE` Twitch sends before closing the connection when
+/// the login credentials are rejected.
+fn is_login_failure(raw_msg: &IrcMessageRaw) -> bool {
+    let Some(text) = raw_msg.command_params.last() else {
+        return false;
+    };
+
+    raw_msg.command_params.first().map(String::as_str) == Some("*")
+        && (text == "Login authentication failed" || text == "Improperly formatted auth")
+}
+
+/// Normalizes a channel name to the lowercase, `#`-prefixed form used on the wire.
+fn channel_name_normalized(name: &str) -> String {
+    format!("#{}", name.trim_start_matches('#').to_ascii_lowercase())
+}
+
+/// Normalizes a channel name like [`channel_name_normalized`], failing with
+/// [`Error::InvalidInput`] if it is empty or contains characters which would end the channel
+/// name, or the whole command, on the wire.
+fn channel_name_checked(name: &str) -> Result<String> {
+    let channel = channel_name_normalized(name);
+    let is_invalid = |c: char| c.is_whitespace() || c == ',' || c == '\0';
+    if channel.len() == 1 || channel.contains(is_invalid) {
+        return Err(Error::InvalidInput(format!(
+            "invalid channel name {:?}",
+            name
+        )));
+    }
+
+    Ok(channel)
+}
+
+/// The details a server sends when confirming the registration of a connection.
+#[derive(Debug, Clone, Default)]
+pub struct ServerInfo {
+    /// The name the server identifies itself with, e.g. `tmi.twitch.tv`.
+    pub server_name: String,
+
+    /// The nickname the connection was registered with.
+    pub nickname: String,
+
+    /// The text of the `001 RPL_WELCOME` reply.
+    pub welcome: String,
+
+    /// The text of the `002 RPL_YOURHOST` reply, if sent.
+    pub host_info: Option<String>,
+
+    /// The text of the `003 RPL_CREATED` reply, if sent.
+    pub created: Option<String>,
+
+    /// The parameters of the `004 RPL_MYINFO` reply after the nickname, if sent: the server name,
+    /// its version, and the supported user and channel modes.
+    pub my_info: Vec<String>,
+
+    /// The lines of the message of the day, if sent.
+    pub motd: Vec<String>,
+}
+
+/// Tracks connection liveness for [`Irc::receive`].
+#[derive(Debug)]
+struct Keepalive {
+    /// How long the connection may stay idle before a client `PING` is sent.
+    ping_interval: Duration,
+
+    /// How long to wait for traffic after a client `PING` before giving up.
+    timeout: Duration,
+
+    /// The last time any line was received from the server.
+    last_activity: Instant,
 
-    /// The body of the message.
-    pub message: String,
+    /// The time the outstanding client `PING` was sent, if there is one.
+    ping_sent_at: Option<Instant>,
+}
+
+impl Keepalive {
+    fn new(ping_interval: Duration, timeout: Duration) -> Self {
+        Self {
+            ping_interval,
+            timeout,
+            last_activity: Instant::now(),
+            ping_sent_at: None,
+        }
+    }
+
+    /// Records that a line was received, which proves the connection is alive.
+    fn record_activity(&mut self) {
+        self.last_activity = Instant::now();
+        self.ping_sent_at = None;
+    }
+
+    /// Returns the instant at which the next client `PING` is due or, if one is already
+    /// outstanding, the instant at which the connection is considered dead.
+    fn deadline(&self) -> Instant {
+        match self.ping_sent_at {
+            Some(ping_sent_at) => ping_sent_at + self.timeout,
+            None => self.last_activity + self.ping_interval,
+        }
+    }
 }
 
 /// Represents an IRC message or event.
@@ -158,144 +1153,783 @@ pub struct PrivMsg {
 pub enum Message {
     /// A private IRC message sent by a user or bot and received in an IRC channel.
     PrivMsg(PrivMsg),
+
+    /// A Twitch event such as a subscription, a raid or an announcement.
+    UserNotice(UserNotice),
+
+    /// A moderator cleared the chat, or timed out or banned a user.
+    ClearChat(ClearChat),
+
+    /// A moderator deleted a single message.
+    ClearMsg(ClearMsg),
+
+    /// A user joined a channel. Only sent by Twitch if the `twitch.tv/membership` capability was
+    /// requested, except for the client's own joins.
+    Join {
+        /// The channel which was joined, including the leading `#`.
+        channel: String,
+
+        /// The nickname of the user who joined.
+        user: String,
+    },
+
+    /// A user left a channel. Only sent by Twitch if the `twitch.tv/membership` capability was
+    /// requested, except for the client's own parts.
+    Part {
+        /// The channel which was left, including the leading `#`.
+        channel: String,
+
+        /// The nickname of the user who left.
+        user: String,
+    },
+
+    /// The users in a channel, as listed by the server after joining it (`353 RPL_NAMREPLY`
+    /// lines up to `366 RPL_ENDOFNAMES`).
+    Names {
+        /// The channel, including the leading `#`.
+        channel: String,
+
+        /// The nicknames of the users, each preceded by their channel mode prefix such as `@` on
+        /// standard IRC servers.
+        names: Vec<String>,
+    },
+
+    /// Feedback from the server, such as the reason a chat message was rejected.
+    Notice(Notice),
+
+    /// The chat settings of a channel, sent after joining it and whenever they change. See
+    /// [`Irc::room_state`] for the complete settings.
+    RoomState(RoomStateUpdate),
+
+    /// The logged-in user's details in a channel, sent after joining it and after each message
+    /// sent to it.
+    UserState(UserState),
+
+    /// The logged-in user's global details, sent once after logging in.
+    GlobalUserState(GlobalUserState),
+
+    /// A line which could not be parsed. Only emitted in lenient mode (see
+    /// [`IrcBuilder::with_lenient_parsing`]).
+    Malformed {
+        /// The offending line, without the trailing CRLF.
+        raw: String,
+
+        /// Why the line could not be parsed.
+        error: ParseError,
+    },
+
+    /// A message whose command is not (yet) understood by the typed layer, such as a numeric
+    /// reply, left in its raw parsed form.
+    Unknown(IrcMessageRaw),
+
+    /// The connection was lost. Only emitted when reconnection is enabled (see
+    /// [`IrcBuilder::with_reconnect`]); the next call to [`Irc::receive`] reconnects.
+    Disconnected {
+        /// A description of why the connection was lost.
+        reason: String,
+    },
+
+    /// The connection was re-established after a [`Message::Disconnected`] event, and every
+    /// channel has been rejoined. Messages sent in the meantime were missed.
+    Reconnected {
+        /// The number of attempts it took to reconnect.
+        attempts: u32,
+    },
 }
 
 impl Message {
-    /// Converts a raw [`IrcMessageRaw`] to a user-friendly [`Message`] if there is a suitable
-    /// variant, returning `Ok(None)` otherwise.
-    fn from_raw_msg(raw_msg: IrcMessageRaw) -> io::Result<Option<Self>> {
+    /// Parses a single line received from the server.
+    ///
+    /// Unlike [`Irc::receive`], this does not combine `NAMES` replies spanning several lines,
+    /// which are returned as [`Message::Unknown`].
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        Self::from_raw_msg(IrcMessageRaw::parse(line)?, line)
+    }
+
+    /// Converts a raw [`IrcMessageRaw`] to a user-friendly [`Message`], falling back to
+    /// [`Message::Unknown`] if there is no suitable variant.
+    ///
+    /// `line` is the line `raw_msg` was parsed from, used for error reporting.
+    fn from_raw_msg(raw_msg: IrcMessageRaw, line: &str) -> Result<Self, ParseError> {
+        let missing = |what: &str| {
+            let line = line.trim_end_matches(['\r', '\n']);
+            ParseError::new(
+                line,
+                line.len(),
+                format!("{} missing {}", raw_msg.command_name, what),
+            )
+        };
+
         match raw_msg.command_name.as_str() {
             "PRIVMSG" => {
-                let username = if let Some(prefix) = raw_msg.prefix {
-                    prefix.split("!").next().unwrap_or("").to_string()
-                } else {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "PRIVMSG missing prefix",
-                    ));
+                let login = match &raw_msg.prefix {
+                    Some(prefix) => prefix.split('!').next().unwrap_or("").to_string(),
+                    None => return Err(missing("prefix")),
+                };
+                let channel = match raw_msg.command_params.first() {
+                    Some(channel) => channel.clone(),
+                    None => return Err(missing("channel")),
+                };
+                let message = match raw_msg.command_params.get(1) {
+                    Some(message) => message.clone(),
+                    None => return Err(missing("message body")),
                 };
 
-                let message = if let Some(message) = raw_msg.command_params.get(1) {
-                    message.clone()
+                let display_name = raw_msg
+                    .tags
+                    .get("display-name")
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map_or_else(|| login.clone(), str::to_string);
+                let user_id = raw_msg.tags.get_non_empty("user-id").map(str::to_string);
+
+                Ok(Self::PrivMsg(PrivMsg {
+                    login,
+                    display_name,
+                    user_id,
+                    channel,
+                    message,
+                    tags: raw_msg.tags,
+                }))
+            }
+            "JOIN" | "PART" => {
+                let user = match &raw_msg.prefix {
+                    Some(prefix) => prefix.split('!').next().unwrap_or("").to_string(),
+                    None => return Err(missing("prefix")),
+                };
+                let channel = match raw_msg.command_params.first() {
+                    Some(channel) => channel.clone(),
+                    None => return Err(missing("channel")),
+                };
+
+                if raw_msg.command_name == "JOIN" {
+                    Ok(Self::Join { channel, user })
                 } else {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "PRIVMSG missing prefix",
-                    ));
+                    Ok(Self::Part { channel, user })
+                }
+            }
+            "USERNOTICE" => {
+                let channel = match raw_msg.command_params.first() {
+                    Some(channel) => channel.clone(),
+                    None => return Err(missing("channel")),
                 };
+                let tags = raw_msg.tags;
+                let login = tags.get("login").unwrap_or_default().to_string();
+                let display_name = tags
+                    .get_non_empty("display-name")
+                    .map_or_else(|| login.clone(), str::to_string);
 
-                Ok(Some(Self::PrivMsg(PrivMsg { username, message })))
+                Ok(Self::UserNotice(UserNotice {
+                    channel,
+                    login,
+                    display_name,
+                    kind: UserNoticeKind::from_tags(&tags),
+                    message: raw_msg.command_params.get(1).cloned(),
+                    system_message: tags.get("system-msg").unwrap_or_default().to_string(),
+                    tags,
+                }))
             }
-            _ => Ok(None),
+            "CLEARCHAT" => {
+                let channel = match raw_msg.command_params.first() {
+                    Some(channel) => channel.clone(),
+                    None => return Err(missing("channel")),
+                };
+
+                Ok(Self::ClearChat(ClearChat {
+                    channel,
+                    action: ClearChatAction::new(raw_msg.command_params.get(1), &raw_msg.tags),
+                    tags: raw_msg.tags,
+                }))
+            }
+            "CLEARMSG" => {
+                let channel = match raw_msg.command_params.first() {
+                    Some(channel) => channel.clone(),
+                    None => return Err(missing("channel")),
+                };
+                let message = match raw_msg.command_params.get(1) {
+                    Some(message) => message.clone(),
+                    None => return Err(missing("message body")),
+                };
+                let tag = |key: &str| raw_msg.tags.get_non_empty(key).map(str::to_string);
+
+                Ok(Self::ClearMsg(ClearMsg {
+                    channel,
+                    login: tag("login"),
+                    target_msg_id: tag("target-msg-id"),
+                    message,
+                    tags: raw_msg.tags,
+                }))
+            }
+            "NOTICE" => {
+                let channel = match raw_msg.command_params.first() {
+                    Some(channel) => channel.clone(),
+                    None => return Err(missing("target")),
+                };
+                let text = match raw_msg.command_params.get(1) {
+                    Some(text) => text.clone(),
+                    None => return Err(missing("text")),
+                };
+
+                Ok(Self::Notice(Notice {
+                    channel: channel.starts_with('#').then_some(channel),
+                    kind: NoticeKind::from_tags(&raw_msg.tags),
+                    text,
+                    tags: raw_msg.tags,
+                }))
+            }
+            "ROOMSTATE" => {
+                let channel = match raw_msg.command_params.first() {
+                    Some(channel) => channel.clone(),
+                    None => return Err(missing("channel")),
+                };
+
+                Ok(Self::RoomState(RoomStateUpdate::new(channel, raw_msg.tags)))
+            }
+            "USERSTATE" => {
+                let channel = match raw_msg.command_params.first() {
+                    Some(channel) => channel.clone(),
+                    None => return Err(missing("channel")),
+                };
+
+                Ok(Self::UserState(UserState::new(channel, raw_msg.tags)))
+            }
+            "GLOBALUSERSTATE" => Ok(Self::GlobalUserState(GlobalUserState::new(raw_msg.tags))),
+            _ => Ok(Self::Unknown(raw_msg)),
         }
     }
 }
 
 /// Represents a raw parsed form of an IRC message.
 #[derive(Debug, Clone)]
-struct IrcMessageRaw {
-    /// A list of key-value pairs of message tags sent by the server.
-    tags: Vec<(String, String)>,
+pub struct IrcMessageRaw {
+    /// The message tags sent by the server.
+    pub tags: Tags,
 
     /// The user's full prefix, without any additional parsing.
     ///
     /// e.g. `user!user@channel.tmi.twitch.tv`
-    prefix: Option<String>,
+    pub prefix: Option<String>,
 
     /// The name of the command, which is either an arbitrary-length string of letters or a string
     /// of 3 digits.
-    command_name: String,
+    pub command_name: String,
 
-    /// A list of parameters to the command.
-    command_params: Vec<String>,
+    /// A list of parameters to the command. The trailing parameter, if any, is included without
+    /// its leading `:`.
+    pub command_params: Vec<String>,
 }
 
 impl IrcMessageRaw {
     /// Parses a raw IRC message.
-    pub fn parse(input: &str) -> io::Result<Self> {
-        let mut chars = input.chars().peekable();
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let line = input.trim_end_matches(['\r', '\n']);
+        // The byte offset of the unparsed remainder `rest` within `line`.
+        let offset = |rest: &str| line.len() - rest.len();
 
-        let mut tags: Vec<(String, String)> = Vec::new();
+        let mut rest = line;
+        let mut tags = Tags::default();
         let mut prefix: Option<String> = None;
 
-        let first_char = chars.peek();
-        if first_char == Some(&'@') {
-            let _ = chars.next();
+        if let Some(tags_str) = rest.strip_prefix('@') {
+            let (tags_str, remainder) = split_word(tags_str);
+            tags = Tags::parse(tags_str);
+            rest = remainder;
+        }
+
+        if let Some(prefix_str) = rest.strip_prefix(':') {
+            let (prefix_str, remainder) = split_word(prefix_str);
+            if prefix_str.is_empty() {
+                return Err(ParseError::new(
+                    line,
+                    offset(rest) + 1,
+                    "expected prefix after ':'",
+                ));
+            }
+            prefix = Some(prefix_str.to_string());
+            rest = remainder;
+        }
+
+        let (command_name, remainder) = split_word(rest);
+        if command_name.is_empty() {
+            return Err(ParseError::new(
+                line,
+                offset(rest),
+                "expected command name, reached end of input",
+            ));
+        }
+        let is_numeric =
+            command_name.len() == 3 && command_name.bytes().all(|b| b.is_ascii_digit());
+        if !is_numeric && !command_name.bytes().all(|b| b.is_ascii_alphabetic()) {
+            return Err(ParseError::new(
+                line,
+                offset(rest),
+                "command name must be letters or a 3-digit number",
+            ));
+        }
+        rest = remainder;
+
+        let mut command_params = Vec::new();
+
+        while !rest.is_empty() {
+            if let Some(trailing) = rest.strip_prefix(':') {
+                command_params.push(trailing.to_string());
+                break;
+            }
+
+            let (command_param, remainder) = split_word(rest);
+            command_params.push(command_param.to_string());
+            rest = remainder;
+        }
+
+        Ok(Self {
+            tags,
+            prefix,
+            command_name: command_name.to_string(),
+            command_params,
+        })
+    }
+}
+
+/// Splits off the first space-delimited word of `input`, returning the word and the remainder
+/// with leading spaces removed.
+///
+/// Only ASCII spaces delimit words in IRC; other whitespace, such as `U+3000 IDEOGRAPHIC SPACE`,
+/// may appear unescaped in tag values and parameters.
+fn split_word(input: &str) -> (&str, &str) {
+    let (word, rest) = input.split_once(' ').unwrap_or((input, ""));
+
+    (word, rest.trim_start_matches(' '))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Lines, Write},
+        net::{Shutdown, TcpListener},
+        sync::mpsc,
+        thread::{self, JoinHandle},
+    };
 
-            let tags_str = (&mut chars)
-                .take_while(|c| !c.is_whitespace())
-                .collect::<String>();
-            let mut tags_chars = tags_str.chars();
+    use super::*;
 
-            loop {
-                let key = (&mut tags_chars)
-                    .take_while(|c| *c != '=')
-                    .collect::<String>();
-                if key.len() == 0 {
+    /// What a test server sends to the client right after `NICK`.
+    const WELCOME: &str =
+        ":tmi.twitch.tv 001 tester :Welcome, GLHF!\r\n:tmi.twitch.tv 376 tester :>\r\n";
+
+    /// The server end of a test connection, which replies to the client's lines as scripted.
+    pub(super) struct ScriptedPeer {
+        writer: TcpStream,
+        lines: Lines<BufReader<TcpStream>>,
+        received: Vec<String>,
+    }
+
+    impl ScriptedPeer {
+        pub(super) fn new(stream: TcpStream) -> Self {
+            Self {
+                writer: stream.try_clone().unwrap(),
+                lines: BufReader::new(stream).lines(),
+                received: Vec::new(),
+            }
+        }
+
+        /// Reads lines until one starts with `trigger`, then sends `reply`.
+        pub(super) fn step(&mut self, trigger: &str, reply: &str) {
+            while let Some(Ok(line)) = self.lines.next() {
+                let is_trigger = line.starts_with(trigger);
+                self.received.push(line);
+                if is_trigger {
                     break;
                 }
+            }
+            self.send(reply);
+        }
 
-                let value = (&mut tags_chars)
-                    .take_while(|c| *c != ';')
-                    .collect::<String>();
-
-                tags.push((key, value));
+        /// Follows each `(trigger, reply)` step of `script` in turn.
+        pub(super) fn run(&mut self, script: &[(&str, &str)]) {
+            for (trigger, reply) in script {
+                self.step(trigger, reply);
             }
         }
 
-        let mut chars = chars.skip_while(|c| c.is_whitespace()).peekable();
+        /// Sends `reply` right away.
+        pub(super) fn send(&mut self, reply: &str) {
+            self.writer.write_all(reply.as_bytes()).unwrap();
+        }
 
-        let first_char = chars.peek();
-        if first_char == Some(&':') {
-            let _ = chars.next();
-            prefix = Some(
-                (&mut chars)
-                    .take_while(|c| !c.is_whitespace())
-                    .collect::<String>(),
-            );
+        /// Reads lines until the client disconnects, returning every line received.
+        pub(super) fn finish(mut self) -> Vec<String> {
+            self.received
+                .extend(self.lines.by_ref().map_while(|line| line.ok()));
+            self.received
         }
 
-        let mut chars = chars.skip_while(|c| c.is_whitespace()).peekable();
+        /// Closes the connection, returning every line received so far.
+        pub(super) fn close(self) -> Vec<String> {
+            let _ = self.writer.shutdown(Shutdown::Both);
+            self.received
+        }
+    }
 
-        let command_name = (&mut chars)
-            .take_while(|c| !c.is_whitespace())
-            .collect::<String>();
-        if command_name.len() == 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "error parsing command name: expected command name, reached end of input",
-            ));
+    /// Handles one accepted test connection, returning the lines it received.
+    type Handler = Box<dyn FnOnce(ScriptedPeer) -> Vec<String> + Send>;
+
+    /// Starts a server on a local port which accepts one connection per handler, each handled on
+    /// its own thread. Returns the server's address and a handle which yields the lines received
+    /// on each connection.
+    fn serve(handlers: Vec<Handler>) -> (SocketAddr, JoinHandle<Vec<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let threads: Vec<_> = handlers
+                .into_iter()
+                .map(|handler| {
+                    let (stream, _) = listener.accept().unwrap();
+                    thread::spawn(move || handler(ScriptedPeer::new(stream)))
+                })
+                .collect();
+
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect()
+        });
+
+        (addr, server)
+    }
+
+    /// Returns a handler which follows `script`, then reads until the client disconnects.
+    fn scripted(script: &'static [(&'static str, &'static str)]) -> Handler {
+        Box::new(move |mut peer| {
+            peer.run(script);
+            peer.finish()
+        })
+    }
+
+    fn connect(addr: SocketAddr, builder: IrcBuilder) -> Irc {
+        builder
+            .with_nickname("tester")
+            .with_password("oauth:x")
+            .connect(addr)
+            .unwrap()
+    }
+
+    /// Receives the next message, which must be a `PRIVMSG`, and returns its text.
+    fn receive_text(irc: &mut Irc) -> String {
+        match irc.receive().unwrap() {
+            Some(Message::PrivMsg(msg)) => msg.message,
+            other => panic!("expected a PRIVMSG, got {:?}", other),
         }
+    }
 
-        let mut command_params = Vec::new();
+    #[test]
+    fn parse_splits_on_ascii_space_only() {
+        let line = "@reply-parent-msg-body=你好\u{3000}世界;id=1 \
+                    :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :a\u{a0}b";
+        let raw_msg = IrcMessageRaw::parse(line).unwrap();
 
-        while (&mut chars).peek().is_some() {
-            let command_param = if chars.peek() == Some(&':') {
-                let _ = chars.next();
+        assert_eq!(
+            raw_msg.tags.get("reply-parent-msg-body"),
+            Some("你好\u{3000}世界")
+        );
+        assert_eq!(raw_msg.command_name, "PRIVMSG");
+        assert_eq!(raw_msg.command_params, ["#dallas", "a\u{a0}b"]);
+    }
 
-                (&mut chars)
-                    .take_while(|c| *c != '\r' && *c != '\n')
-                    .collect::<String>()
-            } else {
-                (&mut chars)
-                    .take_while(|c| !c.is_whitespace() && *c != '\r' && *c != '\n')
-                    .collect::<String>()
-            };
+    #[test]
+    fn parse_keeps_non_ascii_whitespace_in_usernotice_tags() {
+        let line = "@msg-id=raid;system-msg=5\u{a0}raiders :tmi.twitch.tv USERNOTICE #dallas";
+        let Ok(Message::UserNotice(notice)) = Message::parse(line) else {
+            panic!("not parsed as a USERNOTICE");
+        };
+
+        assert_eq!(notice.system_message, "5\u{a0}raiders");
+    }
 
-            command_params.push(command_param);
+    #[test]
+    fn parse_reports_missing_prefix_offset() {
+        let error = IrcMessageRaw::parse(": PRIVMSG #a :x").unwrap_err();
 
-            if chars.peek() == Some(&'\r') || chars.peek() == Some(&'\n') {
-                let _ = (&mut chars)
-                    .take_while(|c| *c == '\r' || *c == '\n')
-                    .collect::<String>();
-            }
+        assert_eq!(error.offset, 1);
+        assert_eq!(error.reason, "expected prefix after ':'");
+
+        let error = IrcMessageRaw::parse("@a=b : PRIVMSG #a :x").unwrap_err();
+        assert_eq!(error.offset, 6);
+    }
+
+    #[test]
+    fn channel_names_are_checked() {
+        assert_eq!(channel_name_checked("#Dallas").unwrap(), "#dallas");
+        assert_eq!(channel_name_checked("dallas").unwrap(), "#dallas");
+
+        for name in ["", "#", "x\r\nPRIVMSG #y :hi", "a b", "a,b", "a\0", "a\t"] {
+            assert!(
+                matches!(channel_name_checked(name), Err(Error::InvalidInput(_))),
+                "{:?} was accepted",
+                name
+            );
         }
+    }
 
-        Ok(Self {
-            tags,
-            prefix,
-            command_name,
-            command_params,
-        })
+    #[test]
+    fn reconnect_hands_over_to_new_connection() {
+        let (joined, joined_rx) = mpsc::channel();
+        let (closed, closed_rx) = mpsc::channel();
+        let (addr, server) = serve(vec![
+            Box::new(move |mut peer| {
+                peer.step("NICK", WELCOME);
+                peer.step(
+                    "JOIN",
+                    "@id=1 :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :before\r\n\
+                     :tmi.twitch.tv RECONNECT\r\n",
+                );
+
+                // Whether or not the client has switched over yet, this line must be delivered.
+                joined_rx.recv().unwrap();
+                peer.send("@id=2 :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :late\r\n");
+                let received = peer.close();
+                closed.send(()).unwrap();
+                received
+            }),
+            Box::new(move |mut peer| {
+                peer.step("NICK", WELCOME);
+                peer.step("JOIN", "");
+                joined.send(()).unwrap();
+
+                closed_rx.recv().unwrap();
+                peer.send(
+                    "@id=1 :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :before\r\n\
+                     @id=3 :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :after\r\n",
+                );
+                peer.finish()
+            }),
+        ]);
+
+        let mut irc = connect(addr, IrcBuilder::default());
+        irc.join("dallas").unwrap();
+        let texts: Vec<String> = (0..3).map(|_| receive_text(&mut irc)).collect();
+        assert_eq!(texts, ["before", "late", "after"]);
+        drop(irc);
+
+        let received = server.join().unwrap();
+        assert_eq!(received[1], ["PASS oauth:x", "NICK tester", "JOIN #dallas"]);
+    }
+
+    #[test]
+    fn failed_handover_keeps_current_connection() {
+        let (failed, failed_rx) = mpsc::channel();
+        let (addr, server) = serve(vec![
+            Box::new(move |mut peer| {
+                peer.step("NICK", WELCOME);
+                peer.step("JOIN", ":tmi.twitch.tv RECONNECT\r\n");
+
+                failed_rx.recv().unwrap();
+                peer.send(":ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :still here\r\n");
+                peer.step(
+                    "PRIVMSG",
+                    ":ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :hello\r\n",
+                );
+                peer.finish()
+            }),
+            Box::new(move |peer| {
+                let received = peer.close();
+                failed.send(()).unwrap();
+                received
+            }),
+        ]);
+
+        let mut irc = connect(addr, IrcBuilder::default());
+        irc.join("dallas").unwrap();
+        assert_eq!(receive_text(&mut irc), "still here");
+
+        while irc
+            .handover
+            .as_ref()
+            .is_some_and(|handover| !handover.is_finished())
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        irc.privmsg("dallas", "hi").unwrap();
+        assert_eq!(receive_text(&mut irc), "hello");
+        assert!(irc.handover.is_none());
+        drop(irc);
+
+        let received = server.join().unwrap();
+        assert_eq!(
+            received[0],
+            [
+                "PASS oauth:x",
+                "NICK tester",
+                "JOIN #dallas",
+                "PRIVMSG #dallas :hi"
+            ]
+        );
+    }
+
+    #[test]
+    fn server_ping_is_answered() {
+        let (addr, server) = serve(vec![scripted(&[
+            ("NICK", WELCOME),
+            ("JOIN", "PING :tmi.twitch.tv\r\n"),
+            (
+                "PONG",
+                ":ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :pong'd\r\n",
+            ),
+        ])]);
+
+        let mut irc = connect(addr, IrcBuilder::default());
+        irc.join("dallas").unwrap();
+        assert_eq!(receive_text(&mut irc), "pong'd");
+        drop(irc);
+
+        let received = server.join().unwrap();
+        assert_eq!(received[0][3..], ["PONG :tmi.twitch.tv"]);
+    }
+
+    #[test]
+    fn idle_connection_is_pinged() {
+        let (addr, server) = serve(vec![scripted(&[
+            ("NICK", WELCOME),
+            (
+                "PING",
+                ":tmi.twitch.tv PONG tmi.twitch.tv :consolation\r\n\
+                 :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :still here\r\n",
+            ),
+        ])]);
+
+        let builder = IrcBuilder::default().with_ping_interval(Duration::from_millis(50));
+        let mut irc = connect(addr, builder);
+        assert_eq!(receive_text(&mut irc), "still here");
+        drop(irc);
+
+        let received = server.join().unwrap();
+        assert_eq!(received[0][2..], ["PING :consolation"]);
+    }
+
+    #[test]
+    fn unanswered_ping_times_out() {
+        let (addr, server) = serve(vec![scripted(&[("NICK", WELCOME)])]);
+
+        let builder = IrcBuilder::default()
+            .with_ping_interval(Duration::from_millis(50))
+            .with_keepalive_timeout(Duration::from_millis(50));
+        let mut irc = connect(addr, builder);
+        assert!(matches!(irc.receive(), Err(Error::Timeout)));
+        drop(irc);
+
+        let received = server.join().unwrap();
+        assert_eq!(received[0][2..], ["PING :consolation"]);
+    }
+
+    #[test]
+    fn lost_connection_is_reported_and_reopened() {
+        let (addr, server) = serve(vec![
+            scripted(&[("NICK", WELCOME), ("JOIN", "ERROR :Closing link\r\n")]),
+            scripted(&[
+                ("NICK", WELCOME),
+                (
+                    "JOIN",
+                    ":ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :welcome back\r\n",
+                ),
+            ]),
+        ]);
+
+        let builder = IrcBuilder::default()
+            .with_reconnect(ReconnectPolicy::default().with_initial_delay(Duration::ZERO));
+        let mut irc = connect(addr, builder);
+        irc.join("Dallas").unwrap();
+
+        assert!(matches!(
+            irc.receive().unwrap(),
+            Some(Message::Disconnected { reason }) if reason.contains("Closing link")
+        ));
+        assert!(matches!(
+            irc.receive().unwrap(),
+            Some(Message::Reconnected { attempts: 1 })
+        ));
+        assert_eq!(receive_text(&mut irc), "welcome back");
+        drop(irc);
+
+        let received = server.join().unwrap();
+        assert_eq!(received[1], ["PASS oauth:x", "NICK tester", "JOIN #dallas"]);
+    }
+
+    #[test]
+    fn rejected_login_is_not_retried() {
+        let (addr, server) = serve(vec![
+            scripted(&[("NICK", WELCOME), ("JOIN", "ERROR :Closing link\r\n")]),
+            scripted(&[(
+                "NICK",
+                ":tmi.twitch.tv NOTICE * :Login authentication failed\r\n",
+            )]),
+        ]);
+
+        let builder = IrcBuilder::default()
+            .with_reconnect(ReconnectPolicy::default().with_initial_delay(Duration::ZERO));
+        let mut irc = connect(addr, builder);
+        irc.join("dallas").unwrap();
+
+        assert!(matches!(
+            irc.receive().unwrap(),
+            Some(Message::Disconnected { .. })
+        ));
+        assert!(matches!(irc.receive(), Err(Error::Authentication(_))));
+        drop(irc);
+
+        // The policy allows unlimited attempts, so a retry would not have returned.
+        let received = server.join().unwrap();
+        assert_eq!(received[1], ["PASS oauth:x", "NICK tester"]);
+    }
+
+    #[test]
+    fn builder_debug_redacts_password() {
+        let builder = IrcBuilder::default()
+            .with_nickname("tester")
+            .with_password("oauth:secret");
+        let debug = format!("{:?}", builder);
+
+        assert!(!debug.contains("secret"), "{}", debug);
+        assert!(debug.contains("<redacted>"), "{}", debug);
+        assert!(debug.contains("tester"), "{}", debug);
+    }
+
+    #[test]
+    fn names_are_merged_and_tracked() {
+        let (addr, server) = serve(vec![scripted(&[
+            ("NICK", WELCOME),
+            (
+                "JOIN",
+                ":tester!tester@tester.tmi.twitch.tv JOIN #dallas\r\n\
+                 :tmi.twitch.tv 353 tester = #dallas :tester @ronni\r\n\
+                 :tmi.twitch.tv 353 tester = #dallas :+fefe lucy\r\n\
+                 :tmi.twitch.tv 366 tester #dallas :End of /NAMES list\r\n\
+                 :ronni!ronni@ronni.tmi.twitch.tv PART #dallas\r\n\
+                 :tmi.twitch.tv 353 tester #dallas\r\n",
+            ),
+        ])]);
+
+        let builder = IrcBuilder::default().with_member_tracking(true);
+        let mut irc = connect(addr, builder);
+        irc.join("dallas").unwrap();
+        let members = |irc: &Irc| irc.members("dallas").unwrap().collect::<Vec<_>>().join(" ");
+
+        assert!(matches!(irc.receive().unwrap(), Some(Message::Join { .. })));
+        let Some(Message::Names { channel, names }) = irc.receive().unwrap() else {
+            panic!("expected the NAMES reply");
+        };
+        assert_eq!(channel, "#dallas");
+        assert_eq!(names, ["tester", "@ronni", "+fefe", "lucy"]);
+        assert_eq!(members(&irc), "fefe lucy ronni tester");
+
+        assert!(matches!(irc.receive().unwrap(), Some(Message::Part { .. })));
+        assert_eq!(members(&irc), "fefe lucy tester");
+
+        let Some(Message::Unknown(raw_msg)) = irc.receive().unwrap() else {
+            panic!("expected the malformed 353 line as an unknown message");
+        };
+        assert_eq!(raw_msg.command_name, "353");
+        drop(irc);
+
+        server.join().unwrap();
     }
 }
@@ -0,0 +1,47 @@
+use super::Tags;
+
+/// A chat badge shown next to a user's name, as listed in the `badges` tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Badge {
+    /// The badge's name, e.g. `moderator`, `subscriber` or `bits`.
+    pub name: String,
+
+    /// The badge's version, e.g. the subscription tier badge (`12`) or the Bits amount (`1000`).
+    pub version: String,
+
+    /// Additional details from the `badge-info` tag, if any, e.g. the exact number of months
+    /// subscribed for the `subscriber` badge.
+    pub info: Option<String>,
+}
+
+/// Parses the badges listed in the `badges` tag, matching each with its entry in the
+/// `badge-info` tag.
+///
+/// Both tags are comma-separated lists of `name/value` pairs, such as
+/// `broadcaster/1,subscriber/12`.
+pub(crate) fn parse(tags: &Tags) -> Vec<Badge> {
+    let pairs = |key: &str| -> Vec<(String, String)> {
+        tags.get(key)
+            .unwrap_or_default()
+            .split(',')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('/').unwrap_or((pair, ""));
+                (name.to_string(), value.to_string())
+            })
+            .collect()
+    };
+    let info = pairs("badge-info");
+
+    pairs("badges")
+        .into_iter()
+        .map(|(name, version)| Badge {
+            info: info
+                .iter()
+                .find(|(info_name, _)| *info_name == name)
+                .map(|(_, info)| info.clone()),
+            name,
+            version,
+        })
+        .collect()
+}
@@ -0,0 +1,76 @@
+/// The prefixes of Twitch's global cheermotes, which are recognized in any channel.
+///
+/// Channels can also define their own cheermotes, which are only listed by Twitch's API and are
+/// not recognized.
+const CHEERMOTE_PREFIXES: &[&str] = &[
+    "Cheer",
+    "DoodleCheer",
+    "BibleThump",
+    "cheerwhal",
+    "Corgo",
+    "Scoops",
+    "uni",
+    "ShowLove",
+    "Party",
+    "SeemsGood",
+    "Pride",
+    "Kappa",
+    "FrankerZ",
+    "HeyGuys",
+    "DansGame",
+    "EleGiggle",
+    "TriHard",
+    "Kreygasm",
+    "4Head",
+    "SwiftRage",
+    "NotLikeThis",
+    "FailFish",
+    "VoHiYo",
+    "PJSalt",
+    "MrDestructoid",
+    "bday",
+    "RIPCheer",
+    "Shamrock",
+    "BitBoss",
+    "Streamlabs",
+    "Muxy",
+    "HolidayCheer",
+    "Goal",
+    "Anon",
+    "Charity",
+];
+
+/// A cheermote in a [`PrivMsg`](super::PrivMsg): a word such as `Cheer100` which spends Bits, as
+/// returned by [`PrivMsg::segments`](super::PrivMsg::segments).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheermote {
+    /// The cheermote's name, e.g. `Cheer` or `Kappa`, as written in the message.
+    pub prefix: String,
+
+    /// The number of Bits spent, e.g. `100`.
+    pub amount: u64,
+
+    /// The word as written in the message, e.g. `Cheer100`.
+    pub text: String,
+}
+
+impl Cheermote {
+    /// Recognizes `word` as a global cheermote followed by a positive amount, ignoring the case of
+    /// the prefix as Twitch does.
+    pub(crate) fn parse(word: &str) -> Option<Self> {
+        let prefix = word.trim_end_matches(|c: char| c.is_ascii_digit());
+        let amount: u64 = word[prefix.len()..].parse().ok()?;
+        let is_known = CHEERMOTE_PREFIXES
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(prefix));
+        if !is_known || amount == 0 {
+            return None;
+        }
+
+        Some(Self {
+            prefix: prefix.to_string(),
+            amount,
+            text: word.to_string(),
+        })
+    }
+}
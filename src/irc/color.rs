@@ -0,0 +1,81 @@
+use std::fmt;
+
+/// The colors Twitch assigns to users who never chose one.
+const DEFAULT_COLORS: [Rgb; 15] = [
+    Rgb::new(0xFF, 0x00, 0x00),
+    Rgb::new(0x00, 0x00, 0xFF),
+    Rgb::new(0x00, 0x80, 0x00),
+    Rgb::new(0xB2, 0x22, 0x22),
+    Rgb::new(0xFF, 0x7F, 0x50),
+    Rgb::new(0x9A, 0xCD, 0x32),
+    Rgb::new(0xFF, 0x45, 0x00),
+    Rgb::new(0x2E, 0x8B, 0x57),
+    Rgb::new(0xDA, 0xA5, 0x20),
+    Rgb::new(0xD2, 0x69, 0x1E),
+    Rgb::new(0x5F, 0x9E, 0xA0),
+    Rgb::new(0x1E, 0x90, 0xFF),
+    Rgb::new(0xFF, 0x69, 0xB4),
+    Rgb::new(0x8A, 0x2B, 0xE2),
+    Rgb::new(0x00, 0xFF, 0x7F),
+];
+
+/// A color with 8-bit red, green and blue components, such as a user's chat color.
+///
+/// ## Example
+/// ```rust
+/// # use consolation::irc::Rgb;
+/// let color = Rgb::from_hex("#1E90FF").unwrap();
+///
+/// assert_eq!(color, Rgb::new(0x1E, 0x90, 0xFF));
+/// assert_eq!(color.to_string(), "#1E90FF");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    /// The red component.
+    pub r: u8,
+
+    /// The green component.
+    pub g: u8,
+
+    /// The blue component.
+    pub b: u8,
+}
+
+impl Rgb {
+    /// Creates a color from its red, green and blue components.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Parses a color in the `#RRGGBB` form used by Twitch, returning `None` if it is invalid.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+        Some(Self::new(component(0)?, component(2)?, component(4)?))
+    }
+
+    /// Returns one of Twitch's default chat colors, picked deterministically from `login` so that
+    /// a user who never chose a color always gets the same one.
+    pub fn fallback_for(login: &str) -> Self {
+        // FNV-1a, which unlike the standard library's hashers is stable across releases.
+        let hash = login
+            .to_ascii_lowercase()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+                (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+            });
+
+        DEFAULT_COLORS[(hash % DEFAULT_COLORS.len() as u64) as usize]
+    }
+}
+
+impl fmt::Display for Rgb {
+    /// Formats the color as `#RRGGBB`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
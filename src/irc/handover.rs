@@ -0,0 +1,165 @@
+use std::{
+    collections::{BTreeSet, HashSet, VecDeque},
+    io::{self, BufRead, BufReader},
+    net::SocketAddr,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::{registration::Handshake, transport::Transport, IrcBuilder, ServerInfo};
+use crate::{Error, Result};
+
+/// How often the handover thread is polled for completion.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the replaced connection is read at most, if the server does not close it.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many message IDs are remembered for deduplication.
+const RECENT_IDS_CAPACITY: usize = 1024;
+
+/// A replacement connection being opened in the background after the server sent `RECONNECT`.
+#[derive(Debug)]
+pub(crate) struct Handover {
+    thread: JoinHandle<Result<Replacement>>,
+}
+
+/// A registered connection ready to replace the current one.
+#[derive(Debug)]
+pub(crate) struct Replacement {
+    pub(crate) conn: BufReader<Transport>,
+
+    /// The capabilities acknowledged on the replacement connection.
+    pub(crate) capabilities: BTreeSet<String>,
+
+    /// The server's welcome details on the replacement connection.
+    pub(crate) server_info: ServerInfo,
+
+    /// The channels joined on the replacement connection.
+    pub(crate) channels: Vec<String>,
+}
+
+impl Handover {
+    /// Starts connecting, registering and joining `channels` on a new connection in a background
+    /// thread.
+    pub(crate) fn start(config: IrcBuilder, addrs: Vec<SocketAddr>, channels: Vec<String>) -> Self {
+        let thread = thread::spawn(move || open_registered(&config, &addrs, channels));
+
+        Self { thread }
+    }
+
+    /// Returns `true` once the background thread has either succeeded or given up.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the background thread, returning the registered connection.
+    pub(crate) fn finish(self) -> Result<Replacement> {
+        self.thread
+            .join()
+            .map_err(|_| Error::Transport(io::Error::other("reconnection thread panicked")))?
+    }
+}
+
+/// Opens a connection, waits for the server to register it with `001 RPL_WELCOME`, then joins
+/// `channels`.
+///
+/// Anything else received during registration duplicates what the current connection already
+/// delivered, and is dropped.
+fn open_registered(
+    config: &IrcBuilder,
+    addrs: &[SocketAddr],
+    channels: Vec<String>,
+) -> Result<Replacement> {
+    let mut conn = BufReader::new(config.open_transport(addrs)?);
+
+    let mut handshake = Handshake::new(&mut conn, config.registration_timeout());
+    let capabilities = handshake.negotiate(config)?;
+    let server_info = handshake.await_welcome()?;
+    if !channels.is_empty() {
+        handshake.send(&format!("JOIN {}", channels.join(",")))?;
+    }
+
+    Ok(Replacement {
+        conn,
+        capabilities,
+        server_info,
+        channels,
+    })
+}
+
+/// The connection replaced by a handover, which keeps being read until the server closes it so
+/// that lines it sent before the switch are not lost.
+#[derive(Debug)]
+pub(crate) struct Draining {
+    conn: BufReader<Transport>,
+
+    /// A partially received line.
+    line: Vec<u8>,
+
+    /// When to stop reading if the server has not closed the connection yet.
+    deadline: Instant,
+}
+
+impl Draining {
+    /// Starts draining `conn`, of which `line` was partially received.
+    pub(crate) fn new(conn: BufReader<Transport>, line: Vec<u8>) -> Self {
+        // Reads must not block the current connection. If the socket cannot be made
+        // non-blocking, `poll` fails right away and the connection is dropped.
+        let _ = conn.get_ref().tcp().set_nonblocking(true);
+
+        Self {
+            conn,
+            line,
+            deadline: Instant::now() + DRAIN_TIMEOUT,
+        }
+    }
+
+    /// Reads every complete line available without blocking, appending them to `lines`.
+    ///
+    /// Returns `false` once the connection is closed, failed or timed out, and should be dropped.
+    pub(crate) fn poll(&mut self, lines: &mut VecDeque<Vec<u8>>) -> bool {
+        loop {
+            match self.conn.read_until(b'\n', &mut self.line) {
+                Ok(0) => return false,
+                Ok(_) if self.line.ends_with(b"\n") => {
+                    lines.push_back(std::mem::take(&mut self.line))
+                }
+                // A final line without a newline, right before the end of stream.
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    return Instant::now() < self.deadline
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+/// Remembers the most recent message IDs in order to drop messages delivered twice, e.g. by both
+/// connections during a handover.
+#[derive(Debug, Default)]
+pub(crate) struct RecentIds {
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+}
+
+impl RecentIds {
+    /// Records `id`, returning `false` if it was already seen.
+    pub(crate) fn insert(&mut self, id: &str) -> bool {
+        if self.ids.contains(id) {
+            return false;
+        }
+
+        if self.order.len() == RECENT_IDS_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.order.push_back(id.to_string());
+        self.ids.insert(id.to_string());
+
+        true
+    }
+}
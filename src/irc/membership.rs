@@ -0,0 +1,92 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The channel membership mode prefixes standard IRC servers put in front of names in `353`
+/// replies, e.g. `@` for channel operators.
+const MODE_PREFIXES: &[char] = &['~', '&', '@', '%', '+'];
+
+/// Collects `NAMES` replies and, if enabled, keeps track of the users in each joined channel.
+#[derive(Debug, Default)]
+pub(crate) struct Membership {
+    /// The names received in `353 RPL_NAMREPLY` lines for each channel, until the terminating
+    /// `366 RPL_ENDOFNAMES`.
+    pending_names: BTreeMap<String, Vec<String>>,
+
+    /// The users currently in each joined channel, or `None` if tracking is disabled.
+    members: Option<BTreeMap<String, BTreeSet<String>>>,
+}
+
+impl Membership {
+    pub(crate) fn new(tracking: bool) -> Self {
+        Self {
+            pending_names: BTreeMap::new(),
+            members: tracking.then(BTreeMap::new),
+        }
+    }
+
+    /// Collects the space-separated `names` of a `353 RPL_NAMREPLY` for `channel`.
+    pub(crate) fn push_names(&mut self, channel: &str, names: &str) {
+        self.pending_names
+            .entry(channel.to_string())
+            .or_default()
+            .extend(names.split_whitespace().map(str::to_string));
+    }
+
+    /// Returns every name collected for `channel` since the last call.
+    pub(crate) fn take_names(&mut self, channel: &str) -> Vec<String> {
+        self.pending_names.remove(channel).unwrap_or_default()
+    }
+
+    /// Returns the users in `channel`, or `None` if tracking is disabled or the channel is not
+    /// joined.
+    pub(crate) fn members(&self, channel: &str) -> Option<&BTreeSet<String>> {
+        self.members.as_ref()?.get(channel)
+    }
+
+    /// Starts tracking `channel`, if tracking is enabled.
+    pub(crate) fn track_channel(&mut self, channel: &str) {
+        if let Some(members) = &mut self.members {
+            members.entry(channel.to_string()).or_default();
+        }
+    }
+
+    /// Stops tracking `channel`.
+    pub(crate) fn forget_channel(&mut self, channel: &str) {
+        if let Some(members) = &mut self.members {
+            members.remove(channel);
+        }
+    }
+
+    /// Forgets the users of every channel, e.g. after reconnecting, when the server sends them
+    /// again.
+    pub(crate) fn clear(&mut self) {
+        if let Some(members) = &mut self.members {
+            members.values_mut().for_each(BTreeSet::clear);
+        }
+        self.pending_names.clear();
+    }
+
+    /// Records that `user` joined `channel`.
+    pub(crate) fn join(&mut self, channel: &str, user: &str) {
+        if let Some(members) = &mut self.members {
+            if let Some(users) = members.get_mut(channel) {
+                users.insert(user.to_ascii_lowercase());
+            }
+        }
+    }
+
+    /// Records that `user` left `channel`.
+    pub(crate) fn part(&mut self, channel: &str, user: &str) {
+        if let Some(members) = &mut self.members {
+            if let Some(users) = members.get_mut(channel) {
+                users.remove(&user.to_ascii_lowercase());
+            }
+        }
+    }
+
+    /// Records the users listed in a complete `NAMES` reply for `channel`.
+    pub(crate) fn names(&mut self, channel: &str, names: &[String]) {
+        for name in names {
+            self.join(channel, name.trim_start_matches(MODE_PREFIXES));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use super::Tags;
+
+/// Represents a Twitch `CLEARCHAT`: a moderator cleared the whole chat, or removed all messages
+/// of a user by timing them out or banning them.
+#[derive(Debug, Clone)]
+pub struct ClearChat {
+    /// The channel the chat was cleared in, including the leading `#`.
+    pub channel: String,
+
+    /// What was cleared.
+    pub action: ClearChatAction,
+
+    /// The IRCv3 tags sent with the event, such as `room-id` and `tmi-sent-ts`.
+    pub tags: Tags,
+}
+
+/// What a [`ClearChat`] removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClearChatAction {
+    /// Every message in the channel was removed.
+    ClearAll,
+
+    /// A user was temporarily prevented from chatting, and their messages were removed.
+    Timeout {
+        /// The login name of the user.
+        login: String,
+
+        /// The user's ID, if sent.
+        user_id: Option<String>,
+
+        /// How long the user cannot chat.
+        duration: Duration,
+    },
+
+    /// A user was permanently banned from the channel, and their messages were removed.
+    Ban {
+        /// The login name of the user.
+        login: String,
+
+        /// The user's ID, if sent.
+        user_id: Option<String>,
+    },
+}
+
+impl ClearChatAction {
+    /// Determines the action from the optional target user parameter and the tags of a
+    /// `CLEARCHAT`. The presence of a `ban-duration` tag distinguishes a timeout from a ban.
+    pub(crate) fn new(login: Option<&String>, tags: &Tags) -> Self {
+        let Some(login) = login else {
+            return Self::ClearAll;
+        };
+        let login = login.clone();
+        let user_id = tags.get_non_empty("target-user-id").map(str::to_string);
+
+        match tags.get_u64("ban-duration") {
+            Some(seconds) => Self::Timeout {
+                login,
+                user_id,
+                duration: Duration::from_secs(seconds),
+            },
+            None => Self::Ban { login, user_id },
+        }
+    }
+
+    /// Returns the login name of the user whose messages were removed, or `None` if the whole
+    /// chat was cleared.
+    pub fn login(&self) -> Option<&str> {
+        match self {
+            Self::ClearAll => None,
+            Self::Timeout { login, .. } | Self::Ban { login, .. } => Some(login),
+        }
+    }
+}
+
+/// Represents a Twitch `CLEARMSG`: a moderator deleted a single message.
+#[derive(Debug, Clone)]
+pub struct ClearMsg {
+    /// The channel the message was deleted in, including the leading `#`.
+    pub channel: String,
+
+    /// The login name of the user who sent the deleted message, if sent.
+    pub login: Option<String>,
+
+    /// The `id` tag of the deleted message, if sent.
+    pub target_msg_id: Option<String>,
+
+    /// The text of the deleted message.
+    pub message: String,
+
+    /// The IRCv3 tags sent with the event.
+    pub tags: Tags,
+}
@@ -0,0 +1,89 @@
+use super::Tags;
+
+/// Represents a `NOTICE`: feedback from the server, such as the reason a chat message was
+/// rejected or the confirmation of a chat setting change.
+#[derive(Debug, Clone)]
+pub struct Notice {
+    /// The channel the notice is about, including the leading `#`, or `None` if it is not about
+    /// a particular channel.
+    pub channel: Option<String>,
+
+    /// What the notice is about.
+    pub kind: NoticeKind,
+
+    /// The human-readable text of the notice.
+    pub text: String,
+
+    /// The IRCv3 tags sent with the notice.
+    pub tags: Tags,
+}
+
+/// The reason for a [`Notice`], determined by its `msg-id` tag.
+///
+/// Only the most common reasons have a variant; see Twitch's documentation for the full list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NoticeKind {
+    /// A message was rejected because the rate limit was exceeded (`msg_ratelimit`).
+    RateLimited,
+
+    /// A message was rejected because the user is banned in the channel (`msg_banned`).
+    Banned,
+
+    /// A message was rejected because the user is timed out in the channel (`msg_timedout`).
+    TimedOut,
+
+    /// A message was rejected because it is identical to the previous one sent within the last 30
+    /// seconds (`msg_duplicate`).
+    Duplicate,
+
+    /// A message was rejected because the channel is in followers-only mode
+    /// (`msg_followersonly`, `msg_followersonly_zero` or `msg_followersonly_followed`).
+    FollowersOnly,
+
+    /// A message was rejected because the channel is in subscribers-only mode (`msg_subsonly`).
+    SubsOnly,
+
+    /// A message was rejected because the channel is in emote-only mode (`msg_emoteonly`).
+    EmoteOnly,
+
+    /// A message was rejected because the channel is in slow mode (`msg_slowmode`).
+    SlowMode,
+
+    /// A message was rejected because the channel is in unique chat mode (`msg_r9k`).
+    UniqueChat,
+
+    /// The channel was suspended and cannot be joined or chatted in (`msg_channel_suspended`).
+    ChannelSuspended,
+
+    /// A message was rejected because the user's account is suspended (`msg_suspended`).
+    Suspended,
+
+    /// A command was rejected because the user lacks the permission to use it (`no_permission`).
+    NoPermission,
+
+    /// Any other notice. Contains the `msg-id` tag, which is empty if the tag was not sent.
+    Other(String),
+}
+
+impl NoticeKind {
+    /// Determines the reason for a notice from its tags.
+    pub(crate) fn from_tags(tags: &Tags) -> Self {
+        match tags.get("msg-id").unwrap_or_default() {
+            "msg_ratelimit" => Self::RateLimited,
+            "msg_banned" => Self::Banned,
+            "msg_timedout" => Self::TimedOut,
+            "msg_duplicate" => Self::Duplicate,
+            "msg_followersonly" | "msg_followersonly_zero" | "msg_followersonly_followed" => {
+                Self::FollowersOnly
+            }
+            "msg_subsonly" => Self::SubsOnly,
+            "msg_emoteonly" => Self::EmoteOnly,
+            "msg_slowmode" => Self::SlowMode,
+            "msg_r9k" => Self::UniqueChat,
+            "msg_channel_suspended" => Self::ChannelSuspended,
+            "msg_suspended" => Self::Suspended,
+            "no_permission" => Self::NoPermission,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// The maximum number of characters Twitch accepts in a single chat message.
+pub const MAX_MESSAGE_LEN: usize = 500;
+
+/// The window over which Twitch counts the messages sent by a user.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(30);
+
+/// The number of messages a regular user may send per window.
+const NORMAL_RATE_LIMIT: usize = 20;
+
+/// The number of messages a moderator, VIP or broadcaster may send per window in their channel.
+const MODERATOR_RATE_LIMIT: usize = 100;
+
+/// The minimum spacing between two messages sent by a regular user to the same channel.
+const CHANNEL_SPACING: Duration = Duration::from_secs(1);
+
+/// Determines what happens when a message longer than [`MAX_MESSAGE_LEN`] characters is sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LongMessagePolicy {
+    /// The message is rejected with [`Error::InvalidInput`](crate::Error::InvalidInput).
+    #[default]
+    Reject,
+
+    /// The message is split into several messages, preferring to break at whitespace.
+    Split,
+}
+
+/// A chat message waiting in the outgoing queue.
+#[derive(Debug, Clone)]
+pub(crate) struct OutgoingMessage {
+    /// The normalized channel name, including the leading `#`.
+    pub(crate) channel: String,
+
+    /// The sanitized message body.
+    pub(crate) text: String,
+
+    /// The `id` tag of the message this one replies to, if any.
+    pub(crate) reply_parent_msg_id: Option<String>,
+}
+
+/// Keeps track of sent messages in order to stay within Twitch's chat rate limits.
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    /// The send times of every message within the current window, oldest first.
+    sent: VecDeque<Instant>,
+
+    /// The time the last message was sent to each channel.
+    last_sent: HashMap<String, Instant>,
+
+    /// The channels in which the user is a moderator, a VIP or the broadcaster.
+    moderator_channels: HashSet<String>,
+}
+
+impl RateLimiter {
+    /// Returns the earliest instant at which a message may be sent to `channel`.
+    pub(crate) fn next_send_at(&mut self, channel: &str, now: Instant) -> Instant {
+        while let Some(sent_at) = self.sent.front() {
+            if now.duration_since(*sent_at) < RATE_LIMIT_WINDOW {
+                break;
+            }
+            self.sent.pop_front();
+        }
+
+        let is_moderator = self.moderator_channels.contains(channel);
+        let limit = if is_moderator {
+            MODERATOR_RATE_LIMIT
+        } else {
+            NORMAL_RATE_LIMIT
+        };
+
+        let mut send_at = now;
+        if self.sent.len() >= limit {
+            send_at = send_at.max(self.sent[self.sent.len() - limit] + RATE_LIMIT_WINDOW);
+        }
+        if !is_moderator {
+            if let Some(last_sent) = self.last_sent.get(channel) {
+                send_at = send_at.max(*last_sent + CHANNEL_SPACING);
+            }
+        }
+
+        send_at
+    }
+
+    /// Records that a message was sent to `channel` at `now`.
+    pub(crate) fn record(&mut self, channel: &str, now: Instant) {
+        self.sent.push_back(now);
+        self.last_sent.insert(channel.to_string(), now);
+    }
+
+    /// Sets whether the user is a moderator, a VIP or the broadcaster in `channel`.
+    pub(crate) fn set_moderator(&mut self, channel: &str, is_moderator: bool) {
+        if is_moderator {
+            self.moderator_channels.insert(channel.to_string());
+        } else {
+            self.moderator_channels.remove(channel);
+        }
+    }
+}
+
+/// Replaces CR and LF characters with spaces so that a message body can never terminate the
+/// current IRC command and inject another one.
+pub(crate) fn sanitize(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+/// Splits `text` into chunks of at most `max_len` characters, breaking at the last whitespace
+/// before the limit where possible.
+pub(crate) fn split(text: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+
+    while rest.chars().count() > max_len {
+        let (limit, c) = rest
+            .char_indices()
+            .nth(max_len)
+            .expect("text is longer than max_len");
+        let split_at = match rest[..limit + c.len_utf8()].rfind(char::is_whitespace) {
+            Some(i) if i > 0 => i,
+            _ => limit,
+        };
+
+        chunks.push(rest[..split_at].trim_end().to_string());
+        rest = rest[split_at..].trim_start();
+    }
+    if !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records `count` messages at `at`, each to a different channel.
+    fn send_many(limiter: &mut RateLimiter, count: usize, at: Instant) {
+        for i in 0..count {
+            limiter.record(&format!("#channel{}", i), at);
+        }
+    }
+
+    #[test]
+    fn normal_limit_is_20_per_30_seconds() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::default();
+
+        send_many(&mut limiter, 19, start);
+        assert_eq!(limiter.next_send_at("#other", start), start);
+
+        limiter.record("#channel19", start);
+        assert_eq!(
+            limiter.next_send_at("#other", start),
+            start + RATE_LIMIT_WINDOW
+        );
+
+        let later = start + RATE_LIMIT_WINDOW;
+        assert_eq!(limiter.next_send_at("#other", later), later);
+    }
+
+    #[test]
+    fn window_slides_with_oldest_message() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::default();
+
+        limiter.record("#a", start);
+        send_many(&mut limiter, 19, start + Duration::from_secs(10));
+
+        let now = start + Duration::from_secs(15);
+        assert_eq!(
+            limiter.next_send_at("#other", now),
+            start + RATE_LIMIT_WINDOW
+        );
+    }
+
+    #[test]
+    fn moderator_limit_is_100_per_30_seconds() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::default();
+        limiter.set_moderator("#mod", true);
+
+        for _ in 0..99 {
+            limiter.record("#mod", start);
+        }
+        assert_eq!(limiter.next_send_at("#mod", start), start);
+
+        limiter.record("#mod", start);
+        assert_eq!(
+            limiter.next_send_at("#mod", start),
+            start + RATE_LIMIT_WINDOW
+        );
+    }
+
+    #[test]
+    fn messages_to_moderated_channels_count_towards_normal_limit() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::default();
+        limiter.set_moderator("#mod", true);
+
+        for _ in 0..20 {
+            limiter.record("#mod", start);
+        }
+        assert_eq!(limiter.next_send_at("#mod", start), start);
+        assert_eq!(
+            limiter.next_send_at("#other", start),
+            start + RATE_LIMIT_WINDOW
+        );
+    }
+
+    #[test]
+    fn regular_channels_are_spaced_by_one_second() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::default();
+
+        limiter.record("#a", start);
+        assert_eq!(limiter.next_send_at("#a", start), start + CHANNEL_SPACING);
+        assert_eq!(limiter.next_send_at("#b", start), start);
+
+        let later = start + Duration::from_millis(400);
+        assert_eq!(limiter.next_send_at("#a", later), start + CHANNEL_SPACING);
+    }
+
+    #[test]
+    fn switching_to_moderator_lifts_spacing() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::default();
+        limiter.record("#a", start);
+
+        limiter.set_moderator("#a", true);
+        assert_eq!(limiter.next_send_at("#a", start), start);
+
+        limiter.set_moderator("#a", false);
+        assert_eq!(limiter.next_send_at("#a", start), start + CHANNEL_SPACING);
+    }
+
+    #[test]
+    fn split_prefers_whitespace() {
+        assert_eq!(split("  one two three  ", 9), ["one two", "three"]);
+        assert_eq!(split("one two", 7), ["one two"]);
+    }
+
+    #[test]
+    fn split_breaks_at_whitespace_right_after_limit() {
+        let text = format!("{} b", "a".repeat(MAX_MESSAGE_LEN));
+
+        assert_eq!(
+            split(&text, MAX_MESSAGE_LEN),
+            ["a".repeat(MAX_MESSAGE_LEN), "b".to_string()]
+        );
+    }
+
+    #[test]
+    fn split_without_whitespace_cuts_at_limit() {
+        let text = "a".repeat(1200);
+        let chunks = split(&text, MAX_MESSAGE_LEN);
+
+        let lens: Vec<_> = chunks.iter().map(String::len).collect();
+        assert_eq!(lens, [500, 500, 200]);
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn split_counts_multibyte_characters() {
+        let text = "日本".repeat(250) + "語😀";
+        let chunks = split(&text, MAX_MESSAGE_LEN);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chars().count(), MAX_MESSAGE_LEN);
+        assert_eq!(chunks[1], "語😀");
+    }
+}
@@ -0,0 +1,457 @@
+use std::ops::Range;
+
+use super::{badges, Badge, Cheermote, ReplyContext, Rgb, Tags};
+
+/// The start of a `/me` message, which ends with another `\x01`.
+const ACTION_PREFIX: &str = "\x01ACTION ";
+
+/// Represents a private IRC message sent by a user or bot and received in an IRC channel.
+#[derive(Debug, Clone)]
+pub struct PrivMsg {
+    /// The login name of the message sender, which is always lowercase.
+    pub login: String,
+
+    /// The name the sender chose to be shown as, from the `display-name` tag, falling back to
+    /// their login name.
+    ///
+    /// It usually differs from the login name only by letter case, but may also be written in
+    /// another script, such as CJK characters (see [`PrivMsg::has_localized_name`]).
+    pub display_name: String,
+
+    /// The sender's user ID, from the `user-id` tag, if sent.
+    pub user_id: Option<String>,
+
+    /// The channel the message was sent to, including the leading `#`.
+    pub channel: String,
+
+    /// The body of the message, as sent. For `/me` messages, this includes the `\x01ACTION `
+    /// wrapper; see [`PrivMsg::text`].
+    pub message: String,
+
+    /// The IRCv3 tags sent with the message, such as `id`, `user-id` and `tmi-sent-ts`.
+    ///
+    /// Tags are only sent by the server if the `twitch.tv/tags` capability was requested.
+    pub tags: Tags,
+}
+
+impl PrivMsg {
+    /// Returns the message's unique ID from the `id` tag, which is needed to reply to it with
+    /// [`Irc::reply`](super::Irc::reply).
+    pub fn id(&self) -> Option<&str> {
+        self.tags.get_non_empty("id")
+    }
+
+    /// Returns the message this one replies to, or `None` if it is not a reply.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use consolation::irc::Message;
+    /// let line = "@id=b34ccfc7;reply-parent-msg-id=a1;reply-parent-user-login=ronni;\
+    ///             reply-parent-display-name=Ronni;reply-parent-msg-body=who\\swon? \
+    ///             :dallas!dallas@dallas.tmi.twitch.tv PRIVMSG #dallas :@Ronni nobody yet";
+    /// let Ok(Message::PrivMsg(msg)) = Message::parse(line) else { unreachable!() };
+    ///
+    /// let reply = msg.reply_context().unwrap();
+    /// assert_eq!(reply.parent_msg_id, "a1");
+    /// assert_eq!(reply.parent_display_name, "Ronni");
+    /// assert_eq!(reply.parent_body, "who won?");
+    /// ```
+    pub fn reply_context(&self) -> Option<ReplyContext> {
+        ReplyContext::from_tags(&self.tags)
+    }
+
+    /// Returns the sender's chat badges, in display order, as listed in the `badges` and
+    /// `badge-info` tags.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use consolation::irc::{Badge, Message};
+    /// let line = "@badge-info=subscriber/14;badges=moderator/1,subscriber/12 \
+    ///             :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :hi";
+    /// let Ok(Message::PrivMsg(msg)) = Message::parse(line) else { unreachable!() };
+    ///
+    /// assert_eq!(
+    ///     msg.badges()[1],
+    ///     Badge {
+    ///         name: "subscriber".to_string(),
+    ///         version: "12".to_string(),
+    ///         info: Some("14".to_string()),
+    ///     }
+    /// );
+    /// assert!(msg.is_moderator());
+    /// assert!(!msg.is_broadcaster());
+    /// assert_eq!(msg.subscriber_months(), Some(14));
+    /// ```
+    pub fn badges(&self) -> Vec<Badge> {
+        badges::parse(&self.tags)
+    }
+
+    /// Returns `true` if the sender is a moderator in the channel.
+    pub fn is_moderator(&self) -> bool {
+        self.tags.get_bool("mod") == Some(true) || self.has_badge("moderator")
+    }
+
+    /// Returns `true` if the sender is a VIP in the channel.
+    pub fn is_vip(&self) -> bool {
+        self.tags.contains("vip") || self.has_badge("vip")
+    }
+
+    /// Returns `true` if the sender is the channel's broadcaster.
+    pub fn is_broadcaster(&self) -> bool {
+        self.has_badge("broadcaster")
+    }
+
+    /// Returns the number of months the sender has been subscribed to the channel, or `None` if
+    /// they are not a subscriber.
+    ///
+    /// This is the exact number from the `badge-info` tag when available, otherwise the one shown
+    /// on the subscriber badge, which is rounded down to the nearest badge tier.
+    pub fn subscriber_months(&self) -> Option<u32> {
+        let badge = self
+            .badges()
+            .into_iter()
+            .find(|badge| badge.name == "subscriber" || badge.name == "founder")?;
+
+        let months = badge.info.as_deref().unwrap_or(&badge.version);
+        Some(months.parse().unwrap_or(0))
+    }
+
+    /// Returns the number of Bits the sender cheered with the message, from the `bits` tag, or
+    /// `None` if it is not a cheer.
+    pub fn bits(&self) -> Option<u64> {
+        self.tags.get_u64("bits").filter(|&bits| bits > 0)
+    }
+
+    /// Returns the sender's chat color from the `color` tag, or `None` if they never chose one.
+    pub fn color(&self) -> Option<Rgb> {
+        Rgb::from_hex(self.tags.get_non_empty("color")?)
+    }
+
+    /// Returns the color to show the sender's name in: their chat color, or a default color
+    /// derived from their login name (see [`Rgb::fallback_for`]) if they never chose one.
+    pub fn display_color(&self) -> Rgb {
+        self.color()
+            .unwrap_or_else(|| Rgb::fallback_for(&self.login))
+    }
+
+    /// Returns `true` if the sender's display name differs from their login name by more than
+    /// letter case, e.g. because it is written in CJK characters, in which case both should be
+    /// shown for the sender to be recognizable.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use consolation::irc::Message;
+    /// let line = "@display-name=小明;user-id=123 \
+    ///             :xiaoming!xiaoming@xiaoming.tmi.twitch.tv PRIVMSG #dallas :hi";
+    /// let Ok(Message::PrivMsg(msg)) = Message::parse(line) else { unreachable!() };
+    ///
+    /// assert_eq!(msg.login, "xiaoming");
+    /// assert_eq!(msg.display_name, "小明");
+    /// assert_eq!(msg.user_id.as_deref(), Some("123"));
+    /// assert!(msg.has_localized_name());
+    /// ```
+    pub fn has_localized_name(&self) -> bool {
+        !self.display_name.eq_ignore_ascii_case(&self.login)
+    }
+
+    /// Returns `true` if the message was sent with `/me`, which is usually shown as an action
+    /// (e.g. "* ronni waves") rather than as speech.
+    pub fn is_action(&self) -> bool {
+        self.message.starts_with(ACTION_PREFIX)
+    }
+
+    /// Returns the text of the message, without the `\x01ACTION ` wrapper of `/me` messages.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use consolation::irc::Message;
+    /// let line = ":ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :\x01ACTION waves\x01";
+    /// let Ok(Message::PrivMsg(msg)) = Message::parse(line) else { unreachable!() };
+    ///
+    /// assert!(msg.is_action());
+    /// assert_eq!(msg.text(), "waves");
+    /// ```
+    pub fn text(&self) -> &str {
+        match self.message.strip_prefix(ACTION_PREFIX) {
+            Some(text) => text.strip_suffix('\x01').unwrap_or(text),
+            None => &self.message,
+        }
+    }
+
+    fn has_badge(&self, name: &str) -> bool {
+        self.badges().iter().any(|badge| badge.name == name)
+    }
+
+    /// Returns the emotes used in the message, in order of appearance, as listed in the `emotes`
+    /// tag.
+    ///
+    /// Twitch measures emote positions in [`PrivMsg::text`], which excludes the wrapper of `/me`
+    /// messages, and in Unicode code points (`char`s), not bytes, so [`Emote::char_range`] cannot
+    /// be used to slice it directly; use [`Emote::text`] or [`PrivMsg::segments`] instead.
+    /// Positions which do not fit the text are ignored.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use consolation::irc::Message;
+    /// let line = "@emotes=25:0-4,18-22/1902:8-12 :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #ronni \
+    ///             :Kappa 😀 Keepo 日本語 Kappa";
+    /// let Ok(Message::PrivMsg(msg)) = Message::parse(line) else { unreachable!() };
+    ///
+    /// let emotes: Vec<_> = msg
+    ///     .emotes()
+    ///     .into_iter()
+    ///     .map(|emote| (emote.id, emote.char_range, emote.text))
+    ///     .collect();
+    /// assert_eq!(
+    ///     emotes,
+    ///     [
+    ///         ("25".to_string(), 0..5, "Kappa".to_string()),
+    ///         ("1902".to_string(), 8..13, "Keepo".to_string()),
+    ///         ("25".to_string(), 18..23, "Kappa".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn emotes(&self) -> Vec<Emote> {
+        let Some(tag) = self.tags.get_non_empty("emotes") else {
+            return Vec::new();
+        };
+
+        let text = self.text();
+        let offsets = char_offsets(text);
+        let char_count = offsets.len() - 1;
+
+        let mut emotes = Vec::new();
+        for emote in tag.split('/') {
+            let Some((id, ranges)) = emote.split_once(':').filter(|(id, _)| !id.is_empty()) else {
+                continue;
+            };
+
+            for range in ranges.split(',') {
+                let Some((start, end)) = range.split_once('-') else {
+                    continue;
+                };
+                let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) else {
+                    continue;
+                };
+                // The tag's ranges include their end.
+                if start > end || end >= char_count {
+                    continue;
+                }
+
+                emotes.push(Emote {
+                    id: id.to_string(),
+                    char_range: start..end + 1,
+                    text: text[offsets[start]..offsets[end + 1]].to_string(),
+                });
+            }
+        }
+        emotes.sort_by_key(|emote| emote.char_range.start);
+
+        emotes
+    }
+
+    /// Splits [`PrivMsg::text`] into plain text, emotes and cheermotes, in order.
+    ///
+    /// Overlapping emote positions are ignored. Cheermotes such as `Cheer100` are only recognized
+    /// in cheers (see [`PrivMsg::bits`]), and only for Twitch's global cheermotes.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use consolation::irc::{Message, Segment};
+    /// let line = "@emotes=300:3-10 :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #ronni \
+    ///             :你好 LUL4Head 🎉🎉";
+    /// let Ok(Message::PrivMsg(msg)) = Message::parse(line) else { unreachable!() };
+    ///
+    /// let segments: Vec<_> = msg
+    ///     .segments()
+    ///     .map(|segment| match segment {
+    ///         Segment::Text(text) => format!("text({})", text),
+    ///         Segment::Emote(emote) => format!("emote {}({})", emote.id, emote.text),
+    ///         Segment::Cheermote(cheer) => format!("cheer {}({})", cheer.amount, cheer.prefix),
+    ///     })
+    ///     .collect();
+    /// assert_eq!(segments, ["text(你好 )", "emote 300(LUL4Head)", "text( 🎉🎉)"]);
+    ///
+    /// let line = "@bits=150;emotes= :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #ronni \
+    ///             :cheer100 great stream Kappa50";
+    /// let Ok(Message::PrivMsg(msg)) = Message::parse(line) else { unreachable!() };
+    ///
+    /// let amounts: Vec<_> = msg
+    ///     .segments()
+    ///     .filter_map(|segment| match segment {
+    ///         Segment::Cheermote(cheer) => Some(cheer.amount),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(msg.bits(), Some(150));
+    /// assert_eq!(amounts, [100, 50]);
+    /// ```
+    pub fn segments(&self) -> impl Iterator<Item = Segment<'_>> {
+        let is_cheer = self.bits().is_some();
+        let text = self.text();
+        let offsets = char_offsets(text);
+        let mut segments = Vec::new();
+        // The byte offset where the current text segment starts, and the index of the first
+        // `char` not covered by an emote yet.
+        let mut text_start = 0;
+        let mut next_char = 0;
+
+        for emote in self.emotes() {
+            if emote.char_range.start < next_char {
+                continue;
+            }
+
+            let start = offsets[emote.char_range.start];
+            if text_start < start {
+                push_text(&mut segments, &text[text_start..start], is_cheer);
+            }
+            text_start = offsets[emote.char_range.end];
+            next_char = emote.char_range.end;
+            segments.push(Segment::Emote(emote));
+        }
+        if text_start < text.len() {
+            push_text(&mut segments, &text[text_start..], is_cheer);
+        }
+
+        segments.into_iter()
+    }
+}
+
+/// Appends `text` to `segments`, splitting off the cheermotes it contains if `is_cheer`.
+fn push_text<'a>(segments: &mut Vec<Segment<'a>>, text: &'a str, is_cheer: bool) {
+    let mut text_start = 0;
+
+    if is_cheer {
+        let mut word_start = None;
+        for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+            if !c.is_whitespace() {
+                word_start.get_or_insert(i);
+                continue;
+            }
+            let Some(start) = word_start.take() else {
+                continue;
+            };
+            let Some(cheermote) = Cheermote::parse(&text[start..i]) else {
+                continue;
+            };
+
+            if text_start < start {
+                segments.push(Segment::Text(&text[text_start..start]));
+            }
+            segments.push(Segment::Cheermote(cheermote));
+            text_start = i;
+        }
+    }
+
+    if text_start < text.len() {
+        segments.push(Segment::Text(&text[text_start..]));
+    }
+}
+
+/// Returns the byte offset of every `char` in `text`, followed by the length of `text`, so that
+/// the `char` range `a..b` corresponds to the byte range `offsets[a]..offsets[b]`.
+fn char_offsets(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect()
+}
+
+/// An emote used in a [`PrivMsg`], as returned by [`PrivMsg::emotes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Emote {
+    /// The emote's ID, which can be used to fetch its image from Twitch's CDN.
+    pub id: String,
+
+    /// The position of the emote in [`PrivMsg::text`], in Unicode code points (`char`s), not
+    /// bytes.
+    pub char_range: Range<usize>,
+
+    /// The text the emote replaces, i.e. its name.
+    pub text: String,
+}
+
+/// A fragment of a [`PrivMsg`], as returned by [`PrivMsg::segments`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// Plain text.
+    Text(&'a str),
+
+    /// An emote.
+    Emote(Emote),
+
+    /// A cheermote, spending Bits.
+    Cheermote(Cheermote),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irc::Message;
+
+    fn privmsg(emotes: &str, message: &str) -> PrivMsg {
+        let line = format!(
+            "@emotes={} :ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #ronni :{}",
+            emotes, message
+        );
+        match Message::parse(&line) {
+            Ok(Message::PrivMsg(msg)) => msg,
+            other => panic!("not parsed as a PRIVMSG: {:?}", other),
+        }
+    }
+
+    fn emote_texts(msg: &PrivMsg) -> Vec<String> {
+        msg.emotes().into_iter().map(|emote| emote.text).collect()
+    }
+
+    #[test]
+    fn emote_positions_exclude_action_wrapper() {
+        let msg = privmsg("25:0-4", "\x01ACTION Kappa\x01");
+
+        assert!(msg.is_action());
+        assert_eq!(msg.text(), "Kappa");
+        assert_eq!(emote_texts(&msg), ["Kappa"]);
+        assert_eq!(msg.segments().collect::<Vec<_>>().len(), 1);
+    }
+
+    #[test]
+    fn action_without_closing_delimiter() {
+        let msg = privmsg("", "\x01ACTION waves");
+
+        assert!(msg.is_action());
+        assert_eq!(msg.text(), "waves");
+    }
+
+    #[test]
+    fn plain_message_is_not_action() {
+        let msg = privmsg("", "ACTION waves");
+
+        assert!(!msg.is_action());
+        assert_eq!(msg.text(), "ACTION waves");
+    }
+
+    #[test]
+    fn out_of_range_emote_positions_are_ignored() {
+        let msg = privmsg("25:0-4,6-10,4-2/1902:6-11", "Kappa Keepo");
+
+        assert_eq!(emote_texts(&msg), ["Kappa", "Keepo"]);
+    }
+
+    #[test]
+    fn overlapping_emote_positions_are_skipped_in_segments() {
+        let msg = privmsg("25:0-4/1902:2-6", "Kappa Keepo");
+
+        assert_eq!(emote_texts(&msg), ["Kappa", "ppa K"]);
+        let segments: Vec<_> = msg.segments().collect();
+        assert!(matches!(&segments[0], Segment::Emote(emote) if emote.id == "25"));
+        assert_eq!(segments[1], Segment::Text(" Keepo"));
+        assert_eq!(segments.len(), 2);
+    }
+
+    #[test]
+    fn malformed_emote_ranges_are_ignored() {
+        let msg = privmsg("25:x-4,0-/:0-1/1902", "Kappa");
+
+        assert!(msg.emotes().is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, SystemTime},
+};
+
+/// Configures automatic reconnection, passed to [`IrcBuilder::with_reconnect`].
+///
+/// Delays between attempts grow exponentially from the initial delay up to the maximum delay,
+/// with random jitter applied so that many clients do not reconnect in lockstep.
+///
+/// ## Example
+/// ```rust
+/// # use consolation::irc::ReconnectPolicy;
+/// # use std::time::Duration;
+/// let policy = ReconnectPolicy::default()
+///     .with_initial_delay(Duration::from_millis(500))
+///     .with_max_delay(Duration::from_secs(30))
+///     .with_max_attempts(10);
+/// ```
+///
+/// [`IrcBuilder::with_reconnect`]: super::IrcBuilder::with_reconnect
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    /// Starts at a 1 second delay, caps it at 60 seconds and retries forever.
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Specifies the delay before the first reconnection attempt.
+    pub fn with_initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+
+        self
+    }
+
+    /// Specifies the maximum delay between two reconnection attempts.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+
+        self
+    }
+
+    /// Specifies how many consecutive attempts are made before giving up. Unlimited by default.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+
+        self
+    }
+
+    /// Returns `true` if no further attempt should be made after `attempts` failed ones.
+    pub(crate) fn is_exhausted(&self, attempts: u32) -> bool {
+        self.max_attempts
+            .is_some_and(|max_attempts| attempts >= max_attempts)
+    }
+
+    /// Returns how long to wait before the attempt numbered `attempt`, starting at zero.
+    ///
+    /// The exponential delay is randomly scaled to between 50% and 100% of its value.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        delay.mul_f64(0.5 + random_fraction() / 2.0)
+    }
+}
+
+/// Returns a pseudo-random number in `[0, 1)`.
+///
+/// This is not cryptographically secure, which is fine for jitter and nickname generation.
+pub(crate) fn random_fraction() -> f64 {
+    (random_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Returns a pseudo-random `u64`, seeded from the standard library's per-process random hasher
+/// keys and the current time.
+pub(crate) fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(since_epoch) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(since_epoch.as_nanos());
+    }
+
+    hasher.finish()
+}
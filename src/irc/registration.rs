@@ -0,0 +1,579 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, BufReader},
+    time::{Duration, Instant},
+};
+
+use super::{is_login_failure, transport::Transport, IrcBuilder, IrcMessageRaw, ServerInfo};
+use crate::{Error, Result};
+
+/// Drives the exchange with the server between opening a connection and handing it to
+/// [`Irc`](super::Irc): capability negotiation, authentication and waiting for replies.
+///
+/// Lines which arrive in the meantime but are not part of the exchange are kept in a backlog so
+/// that they can still be delivered by [`Irc::receive`](super::Irc::receive).
+pub(crate) struct Handshake<'a> {
+    conn: &'a mut BufReader<Transport>,
+    deadline: Instant,
+    backlog: Vec<Vec<u8>>,
+}
+
+impl<'a> Handshake<'a> {
+    /// Starts a handshake on `conn` which must complete within `timeout`.
+    pub(crate) fn new(conn: &'a mut BufReader<Transport>, timeout: Duration) -> Self {
+        Self {
+            conn,
+            deadline: Instant::now() + timeout,
+            backlog: Vec::new(),
+        }
+    }
+
+    /// Negotiates the capabilities configured on the builder and authenticates, returning the
+    /// set of capabilities acknowledged by the server.
+    ///
+    /// Fails with [`Error::CapabilityRejected`] if a required capability was rejected or not
+    /// advertised by the server.
+    pub(crate) fn negotiate(&mut self, config: &IrcBuilder) -> Result<BTreeSet<String>> {
+        let mut requested = config.capabilities.clone();
+        if config.capability_negotiation {
+            self.send("CAP LS 302")?;
+        } else if !requested.is_empty() {
+            self.send(&format!("CAP REQ :{}", requested.join(" ")))?;
+        }
+        if let Some(password) = &config.password {
+            self.send(&format!("PASS {}", password))?;
+        }
+        if let Some(nickname) = &config.nickname {
+            self.send(&format!("NICK {}", nickname))?;
+        }
+
+        let mut rejected = Vec::new();
+        if config.capability_negotiation {
+            let available = self.list_capabilities()?;
+            (requested, rejected) = requested
+                .into_iter()
+                .partition(|capability| available.contains(capability));
+
+            if !requested.is_empty() {
+                self.send(&format!("CAP REQ :{}", requested.join(" ")))?;
+            }
+        }
+
+        let mut acknowledged = BTreeSet::new();
+        if !requested.is_empty() {
+            let (is_ack, capabilities) = self.wait_for(|raw_msg| match cap_reply(raw_msg) {
+                Some(("ACK", _, capabilities)) => Step::Done((true, capabilities.to_string())),
+                Some(("NAK", _, capabilities)) => Step::Done((false, capabilities.to_string())),
+                _ if is_unknown_cap_command(raw_msg) => Step::Done((false, String::new())),
+                _ => Step::Skip,
+            })?;
+
+            if is_ack {
+                for capability in capabilities.split_whitespace() {
+                    match capability.strip_prefix('-') {
+                        Some(disabled) => acknowledged.remove(disabled),
+                        None => acknowledged.insert(capability.to_string()),
+                    };
+                }
+            }
+            rejected.extend(
+                requested
+                    .into_iter()
+                    .filter(|capability| !acknowledged.contains(capability)),
+            );
+        }
+
+        if config.capability_negotiation {
+            self.send("CAP END")?;
+        }
+
+        let missing: Vec<String> = config
+            .required_capabilities
+            .iter()
+            .filter(|capability| rejected.contains(capability))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::CapabilityRejected(missing));
+        }
+
+        Ok(acknowledged)
+    }
+
+    /// Reads the (possibly multi-line) reply to `CAP LS`, returning the names of the advertised
+    /// capabilities without their values.
+    fn list_capabilities(&mut self) -> Result<BTreeSet<String>> {
+        let mut available = BTreeSet::new();
+
+        loop {
+            let (more, capabilities) = self.wait_for(|raw_msg| match cap_reply(raw_msg) {
+                Some(("LS", more, capabilities)) => Step::Done((more, capabilities.to_string())),
+                _ if is_unknown_cap_command(raw_msg) => Step::Done((false, String::new())),
+                _ => Step::Skip,
+            })?;
+
+            available.extend(capabilities.split_whitespace().map(|capability| {
+                let name = capability
+                    .split_once('=')
+                    .map_or(capability, |(name, _)| name);
+                name.to_string()
+            }));
+
+            if !more {
+                return Ok(available);
+            }
+        }
+    }
+
+    /// Waits until the server confirms registration with `001 RPL_WELCOME`, then collects the
+    /// welcome numerics and the message of the day, up to its end or the first line which is not
+    /// a numeric reply.
+    ///
+    /// Fails with [`Error::Authentication`] if the server rejects the login instead.
+    pub(crate) fn await_welcome(&mut self) -> Result<ServerInfo> {
+        let mut server_info = self.wait_for(|raw_msg| match raw_msg.command_name.as_str() {
+            "001" => Step::Done(ServerInfo {
+                server_name: raw_msg.prefix.clone().unwrap_or_default(),
+                nickname: raw_msg.command_params.first().cloned().unwrap_or_default(),
+                welcome: raw_msg.command_params.last().cloned().unwrap_or_default(),
+                ..ServerInfo::default()
+            }),
+            _ => Step::Skip,
+        })?;
+
+        let motd = self.wait_for(|raw_msg| {
+            let text = || raw_msg.command_params.last().cloned().unwrap_or_default();
+            match raw_msg.command_name.as_str() {
+                "002" => server_info.host_info = Some(text()),
+                "003" => server_info.created = Some(text()),
+                "004" => {
+                    server_info.my_info = raw_msg.command_params.iter().skip(1).cloned().collect()
+                }
+                "375" => {}
+                "372" => server_info
+                    .motd
+                    .push(text().trim_start_matches("- ").to_string()),
+                "376" | "422" => return Step::Done(()),
+                // Other numerics, such as `005 RPL_ISUPPORT`, belong to the registration burst,
+                // which the message of the day usually follows.
+                name if name.bytes().all(|b| b.is_ascii_digit()) => return Step::Skip,
+                // Servers which send no end of the message of the day at all are done
+                // registering once anything else arrives.
+                _ => return Step::Stop(()),
+            }
+
+            Step::Consumed
+        });
+        match motd {
+            // Not every server sends the end of the message of the day, and registration already
+            // succeeded with `001`.
+            Ok(()) | Err(Error::RegistrationTimeout) => Ok(server_info),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes a single line to the server.
+    pub(crate) fn send(&mut self, line: &str) -> Result<()> {
+        Ok(self.conn.get_mut().send_line(line)?)
+    }
+
+    /// Reads lines until `f` returns [`Step::Done`] or [`Step::Stop`] for one of them, answering `PING`s along the
+    /// way and keeping skipped lines in the backlog. Lines already in the backlog are checked
+    /// first.
+    pub(crate) fn wait_for<T>(
+        &mut self,
+        mut f: impl FnMut(&IrcMessageRaw) -> Step<T>,
+    ) -> Result<T> {
+        let mut i = 0;
+        while i < self.backlog.len() {
+            let line = String::from_utf8_lossy(&self.backlog[i]).into_owned();
+            let step = match IrcMessageRaw::parse(&line) {
+                Ok(raw_msg) => f(&raw_msg),
+                Err(_) => Step::Skip,
+            };
+
+            match step {
+                Step::Done(value) => {
+                    self.backlog.remove(i);
+                    return Ok(value);
+                }
+                // Lines in the backlog were received earlier, so they cannot end the wait.
+                Step::Stop(_) | Step::Skip => i += 1,
+                Step::Consumed => {
+                    self.backlog.remove(i);
+                }
+            }
+        }
+
+        let mut buf = Vec::new();
+        loop {
+            let now = Instant::now();
+            if now >= self.deadline {
+                return Err(Error::RegistrationTimeout);
+            }
+
+            self.conn
+                .get_ref()
+                .tcp()
+                .set_read_timeout(Some(self.deadline - now))?;
+            match self.conn.read_until(b'\n', &mut buf) {
+                Ok(0) => return Err(Error::Disconnected("connection closed".into())),
+                Ok(_) => {}
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            let bytes = std::mem::take(&mut buf);
+            let line = String::from_utf8_lossy(&bytes).into_owned();
+            let Ok(raw_msg) = IrcMessageRaw::parse(&line) else {
+                self.backlog.push(bytes);
+                continue;
+            };
+
+            let reason = || raw_msg.command_params.last().cloned().unwrap_or_default();
+            match raw_msg.command_name.as_str() {
+                "PING" => {
+                    self.send(&format!("PONG :{}", reason()))?;
+                    continue;
+                }
+                "ERROR" => return Err(Error::Disconnected(reason())),
+                "NOTICE" if is_login_failure(&raw_msg) => {
+                    return Err(Error::Authentication(reason()))
+                }
+                _ => {}
+            }
+
+            match f(&raw_msg) {
+                Step::Done(value) => return Ok(value),
+                Step::Stop(value) => {
+                    self.backlog.push(bytes);
+                    return Ok(value);
+                }
+                Step::Consumed => {}
+                Step::Skip => self.backlog.push(bytes),
+            }
+        }
+    }
+
+    /// Ends the handshake, returning the lines which were received but not consumed by it.
+    pub(crate) fn into_backlog(self) -> Vec<Vec<u8>> {
+        self.backlog
+    }
+}
+
+/// What [`Handshake::wait_for`] does with a line, as decided by its callback.
+pub(crate) enum Step<T> {
+    /// Stop waiting, returning the value.
+    Done(T),
+
+    /// Stop waiting, returning the value, and keep the line in the backlog. Only applies to newly
+    /// received lines; lines already in the backlog are skipped.
+    Stop(T),
+
+    /// The line was handled; keep waiting.
+    Consumed,
+
+    /// The line is unrelated; keep it in the backlog and keep waiting.
+    Skip,
+}
+
+/// Splits a `CAP` reply into its subcommand, whether more lines follow, and the list of
+/// capabilities, e.g. `CAP * LS * :a b` yields `("LS", true, "a b")`.
+fn cap_reply(raw_msg: &IrcMessageRaw) -> Option<(&str, bool, &str)> {
+    if raw_msg.command_name != "CAP" {
+        return None;
+    }
+
+    match raw_msg.command_params.as_slice() {
+        [_, subcommand, more, capabilities] if more == "*" => {
+            Some((subcommand, true, capabilities))
+        }
+        [_, subcommand, capabilities] => Some((subcommand, false, capabilities)),
+        _ => None,
+    }
+}
+
+/// Returns `true` if `raw_msg` is an `ERR_UNKNOWNCOMMAND` reply to `CAP`, as sent by servers
+/// which do not support capabilities at all.
+fn is_unknown_cap_command(raw_msg: &IrcMessageRaw) -> bool {
+    raw_msg.command_name == "421"
+        && raw_msg.command_params.get(1).map(String::as_str) == Some("CAP")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread::{self, JoinHandle},
+    };
+
+    use super::{super::tests::ScriptedPeer, *};
+
+    /// Starts a server which, for each `(trigger, reply)` step in turn, reads lines until one
+    /// starts with `trigger` and then sends `reply`. Returns the client end of the connection and
+    /// a handle which yields every line the server received once the client disconnects.
+    fn scripted(
+        script: &[(&'static str, &'static str)],
+    ) -> (BufReader<Transport>, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let script = script.to_vec();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut peer = ScriptedPeer::new(stream);
+            peer.run(&script);
+            peer.finish()
+        });
+
+        (BufReader::new(Transport::Plain(client)), server)
+    }
+
+    /// Negotiates with a server following `script`, returning the result, the lines left in the
+    /// backlog and the lines the server received.
+    fn negotiate(
+        config: &IrcBuilder,
+        script: &[(&'static str, &'static str)],
+    ) -> (Result<BTreeSet<String>>, Vec<String>, Vec<String>) {
+        let (mut conn, server) = scripted(script);
+
+        let mut handshake = Handshake::new(&mut conn, Duration::from_secs(5));
+        let result = handshake.negotiate(config);
+        let backlog = handshake
+            .into_backlog()
+            .iter()
+            .map(|line| String::from_utf8_lossy(line).trim_end().to_string())
+            .collect();
+        drop(conn);
+
+        (result, backlog, server.join().unwrap())
+    }
+
+    /// Registers with a server following `script`, returning the result, the lines left in the
+    /// backlog and the lines the server received.
+    fn register(
+        script: &[(&'static str, &'static str)],
+        timeout: Duration,
+    ) -> (Result<ServerInfo>, Vec<String>, Vec<String>) {
+        let config = IrcBuilder::default()
+            .with_nickname("n")
+            .with_password("oauth:x");
+        let (mut conn, server) = scripted(script);
+
+        let mut handshake = Handshake::new(&mut conn, timeout);
+        let result = handshake
+            .negotiate(&config)
+            .and_then(|_| handshake.await_welcome());
+        let backlog = handshake
+            .into_backlog()
+            .iter()
+            .map(|line| String::from_utf8_lossy(line).trim_end().to_string())
+            .collect();
+        drop(conn);
+
+        (result, backlog, server.join().unwrap())
+    }
+
+    fn capabilities(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn acknowledged_capabilities_are_returned() {
+        let config = IrcBuilder::default()
+            .with_nickname("n")
+            .with_password("oauth:x")
+            .with_capability("twitch.tv/tags")
+            .with_capability("twitch.tv/commands");
+
+        let (result, backlog, received) = negotiate(
+            &config,
+            &[(
+                "NICK",
+                ":tmi.twitch.tv NOTICE * :hello\r\n\
+                 :tmi.twitch.tv CAP * ACK :twitch.tv/tags twitch.tv/commands\r\n",
+            )],
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            capabilities(&["twitch.tv/commands", "twitch.tv/tags"])
+        );
+        assert_eq!(backlog, [":tmi.twitch.tv NOTICE * :hello"]);
+        assert_eq!(
+            received,
+            [
+                "CAP REQ :twitch.tv/tags twitch.tv/commands",
+                "PASS oauth:x",
+                "NICK n"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejected_optional_capabilities_are_left_out() {
+        let config = IrcBuilder::default()
+            .with_nickname("n")
+            .with_capability("a");
+
+        let (result, _, _) = negotiate(&config, &[("NICK", ":srv CAP * NAK :a\r\n")]);
+
+        assert_eq!(result.unwrap(), capabilities(&[]));
+    }
+
+    #[test]
+    fn rejected_required_capability_fails() {
+        let config = IrcBuilder::default()
+            .with_nickname("n")
+            .with_capability("a")
+            .with_required_capability("b");
+
+        let (result, _, _) = negotiate(&config, &[("NICK", ":srv CAP * NAK :a b\r\n")]);
+
+        assert!(matches!(result, Err(Error::CapabilityRejected(missing)) if missing == ["b"]));
+    }
+
+    #[test]
+    fn multi_line_capability_list_is_collected() {
+        let config = IrcBuilder::default()
+            .with_nickname("n")
+            .with_capability_negotiation(true)
+            .with_capability("a")
+            .with_capability("c")
+            .with_capability("z");
+
+        let (result, _, received) = negotiate(
+            &config,
+            &[
+                (
+                    "NICK",
+                    ":srv CAP * LS * :a b=1\r\n:srv CAP * LS :c sasl=PLAIN\r\n",
+                ),
+                ("CAP REQ", ":srv CAP * ACK :a c\r\n"),
+            ],
+        );
+
+        assert_eq!(result.unwrap(), capabilities(&["a", "c"]));
+        assert_eq!(
+            received,
+            ["CAP LS 302", "NICK n", "CAP REQ :a c", "CAP END"]
+        );
+    }
+
+    #[test]
+    fn required_capability_not_advertised_fails() {
+        let config = IrcBuilder::default()
+            .with_nickname("n")
+            .with_capability_negotiation(true)
+            .with_capability("a")
+            .with_required_capability("x");
+
+        let (result, _, received) = negotiate(
+            &config,
+            &[
+                ("NICK", ":srv CAP * LS :a\r\n"),
+                ("CAP REQ", ":srv CAP * ACK :a\r\n"),
+            ],
+        );
+
+        assert!(matches!(result, Err(Error::CapabilityRejected(missing)) if missing == ["x"]));
+        assert_eq!(received, ["CAP LS 302", "NICK n", "CAP REQ :a", "CAP END"]);
+    }
+
+    #[test]
+    fn unknown_cap_command_means_no_capabilities() {
+        let config = IrcBuilder::default()
+            .with_nickname("n")
+            .with_capability_negotiation(true)
+            .with_capability("a");
+
+        let (result, _, received) =
+            negotiate(&config, &[("NICK", ":srv 421 n CAP :Unknown command\r\n")]);
+
+        assert_eq!(result.unwrap(), capabilities(&[]));
+        assert_eq!(received, ["CAP LS 302", "NICK n", "CAP END"]);
+
+        let config = IrcBuilder::default()
+            .with_nickname("n")
+            .with_capability("a");
+
+        let (result, _, _) = negotiate(&config, &[("NICK", ":srv 421 n CAP :Unknown command\r\n")]);
+
+        assert_eq!(result.unwrap(), capabilities(&[]));
+    }
+
+    #[test]
+    fn welcome_and_motd_are_collected() {
+        let (result, backlog, received) = register(
+            &[(
+                "NICK",
+                ":tmi.twitch.tv 001 n :Welcome, GLHF!\r\n\
+                 :tmi.twitch.tv 002 n :Your host is tmi.twitch.tv\r\n\
+                 :tmi.twitch.tv 003 n :This server is rather new\r\n\
+                 :tmi.twitch.tv 004 n tmi.twitch.tv 1.0 o nt\r\n\
+                 :tmi.twitch.tv 005 n CHANTYPES=# :are supported\r\n\
+                 :tmi.twitch.tv 375 n :-\r\n\
+                 :tmi.twitch.tv 372 n :- You are in a maze of twisty passages.\r\n\
+                 :tmi.twitch.tv 372 n :- All alike.\r\n\
+                 :tmi.twitch.tv 376 n :>\r\n\
+                 :tmi.twitch.tv NOTICE * :after\r\n",
+            )],
+            Duration::from_secs(5),
+        );
+
+        let server_info = result.unwrap();
+        assert_eq!(server_info.server_name, "tmi.twitch.tv");
+        assert_eq!(server_info.nickname, "n");
+        assert_eq!(server_info.welcome, "Welcome, GLHF!");
+        assert_eq!(
+            server_info.host_info.as_deref(),
+            Some("Your host is tmi.twitch.tv")
+        );
+        assert_eq!(
+            server_info.created.as_deref(),
+            Some("This server is rather new")
+        );
+        assert_eq!(server_info.my_info, ["tmi.twitch.tv", "1.0", "o", "nt"]);
+        assert_eq!(
+            server_info.motd,
+            ["You are in a maze of twisty passages.", "All alike."]
+        );
+        assert_eq!(backlog, [":tmi.twitch.tv 005 n CHANTYPES=# :are supported"]);
+        assert_eq!(received, ["PASS oauth:x", "NICK n"]);
+    }
+
+    #[test]
+    fn login_failure_is_an_authentication_error() {
+        for (reason, reply) in [
+            (
+                "Login authentication failed",
+                ":tmi.twitch.tv NOTICE * :Login authentication failed\r\n",
+            ),
+            (
+                "Improperly formatted auth",
+                ":tmi.twitch.tv NOTICE * :Improperly formatted auth\r\n",
+            ),
+        ] {
+            let (result, _, _) = register(&[("NICK", reply)], Duration::from_secs(5));
+
+            assert!(
+                matches!(&result, Err(Error::Authentication(text)) if text == reason),
+                "{:?}",
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn silent_server_times_out() {
+        let (result, _, received) = register(&[], Duration::from_millis(100));
+
+        assert!(matches!(result, Err(Error::RegistrationTimeout)));
+        assert_eq!(received, ["PASS oauth:x", "NICK n"]);
+    }
+}
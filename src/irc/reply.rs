@@ -0,0 +1,51 @@
+use super::Tags;
+
+/// The message a [`PrivMsg`](super::PrivMsg) replies to, as returned by
+/// [`PrivMsg::reply_context`](super::PrivMsg::reply_context).
+///
+/// Twitch starts the body of a reply with an `@login` mention of the parent message's sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplyContext {
+    /// The `id` tag of the message being replied to.
+    pub parent_msg_id: String,
+
+    /// The login name of the sender of the message being replied to.
+    pub parent_login: String,
+
+    /// The display name of the sender of the message being replied to, falling back to their
+    /// login name.
+    pub parent_display_name: String,
+
+    /// The user ID of the sender of the message being replied to, if sent.
+    pub parent_user_id: Option<String>,
+
+    /// The text of the message being replied to.
+    pub parent_body: String,
+
+    /// The `id` tag of the first message of the reply thread, if sent. It differs from
+    /// [`ReplyContext::parent_msg_id`] when replying to a reply.
+    pub thread_parent_msg_id: Option<String>,
+
+    /// The login name of the sender of the first message of the reply thread, if sent.
+    pub thread_parent_login: Option<String>,
+}
+
+impl ReplyContext {
+    /// Reads the `reply-parent-*` and `reply-thread-parent-*` tags, returning `None` if the
+    /// message is not a reply.
+    pub(crate) fn from_tags(tags: &Tags) -> Option<Self> {
+        let tag = |key: &str| tags.get_non_empty(key).map(str::to_string);
+        let parent_login = tag("reply-parent-user-login")?;
+
+        Some(Self {
+            parent_msg_id: tag("reply-parent-msg-id")?,
+            parent_display_name: tag("reply-parent-display-name")
+                .unwrap_or_else(|| parent_login.clone()),
+            parent_login,
+            parent_user_id: tag("reply-parent-user-id"),
+            parent_body: tag("reply-parent-msg-body").unwrap_or_default(),
+            thread_parent_msg_id: tag("reply-thread-parent-msg-id"),
+            thread_parent_login: tag("reply-thread-parent-user-login"),
+        })
+    }
+}
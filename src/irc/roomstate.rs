@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use super::Tags;
+
+/// The chat settings of a Twitch channel, as maintained by [`Irc`](super::Irc) from the
+/// `ROOMSTATE` messages it receives (see [`Irc::room_state`](super::Irc::room_state)).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoomState {
+    /// The channel's user ID.
+    pub room_id: Option<String>,
+
+    /// Whether only messages consisting entirely of emotes are allowed.
+    pub emote_only: bool,
+
+    /// How long users must have followed the channel before they can chat, or `None` if
+    /// followers-only mode is off. A zero duration means any follower can chat.
+    pub followers_only: Option<Duration>,
+
+    /// Whether messages must be unique (also known as R9K mode).
+    pub unique_chat: bool,
+
+    /// How long users must wait between sending two messages, or `None` if slow mode is off.
+    pub slow: Option<Duration>,
+
+    /// Whether only subscribers can chat.
+    pub subs_only: bool,
+}
+
+impl RoomState {
+    /// Applies the settings present in `update`, leaving the others unchanged.
+    pub fn apply(&mut self, update: &RoomStateUpdate) {
+        if let Some(room_id) = &update.room_id {
+            self.room_id = Some(room_id.clone());
+        }
+        if let Some(emote_only) = update.emote_only {
+            self.emote_only = emote_only;
+        }
+        if let Some(followers_only) = update.followers_only {
+            self.followers_only = followers_only;
+        }
+        if let Some(unique_chat) = update.unique_chat {
+            self.unique_chat = unique_chat;
+        }
+        if let Some(slow) = update.slow {
+            self.slow = slow;
+        }
+        if let Some(subs_only) = update.subs_only {
+            self.subs_only = subs_only;
+        }
+    }
+}
+
+/// Represents a Twitch `ROOMSTATE`: the chat settings of a channel.
+///
+/// All settings are sent after joining a channel, but only the changed ones afterwards, so every
+/// field is `None` unless it was present in the message. Use [`RoomState::apply`] (or
+/// [`Irc::room_state`](super::Irc::room_state), which does it automatically) to keep track of the
+/// complete state.
+#[derive(Debug, Clone)]
+pub struct RoomStateUpdate {
+    /// The channel the settings apply to, including the leading `#`.
+    pub channel: String,
+
+    /// The channel's user ID.
+    pub room_id: Option<String>,
+
+    /// Whether emote-only mode was turned on or off.
+    pub emote_only: Option<bool>,
+
+    /// The new followers-only setting: `Some(None)` if it was turned off, or `Some(Some(_))` with
+    /// the minimum follow duration if it was turned on.
+    pub followers_only: Option<Option<Duration>>,
+
+    /// Whether unique chat mode was turned on or off.
+    pub unique_chat: Option<bool>,
+
+    /// The new slow mode setting: `Some(None)` if it was turned off, or `Some(Some(_))` with the
+    /// delay between messages if it was turned on.
+    pub slow: Option<Option<Duration>>,
+
+    /// Whether subscribers-only mode was turned on or off.
+    pub subs_only: Option<bool>,
+
+    /// The IRCv3 tags sent with the message.
+    pub tags: Tags,
+}
+
+impl RoomStateUpdate {
+    /// Reads the settings present in the tags of a `ROOMSTATE`.
+    pub(crate) fn new(channel: String, tags: Tags) -> Self {
+        // Followers-only mode is off at -1 and otherwise measured in minutes.
+        let followers_only = tags.get_i64("followers-only").map(|minutes| {
+            u64::try_from(minutes)
+                .ok()
+                .map(|minutes| Duration::from_secs(minutes * 60))
+        });
+        // Slow mode is off at 0 and otherwise measured in seconds.
+        let slow = tags
+            .get_u64("slow")
+            .map(|seconds| (seconds > 0).then(|| Duration::from_secs(seconds)));
+
+        Self {
+            channel,
+            room_id: tags.get_non_empty("room-id").map(str::to_string),
+            emote_only: tags.get_bool("emote-only"),
+            followers_only,
+            unique_chat: tags.get_bool("r9k"),
+            slow,
+            subs_only: tags.get_bool("subs-only"),
+            tags,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irc::Message;
+
+    fn update(tags: &str) -> RoomStateUpdate {
+        let line = format!("@{} :tmi.twitch.tv ROOMSTATE #dallas", tags);
+        match Message::parse(&line) {
+            Ok(Message::RoomState(update)) => update,
+            other => panic!("not parsed as a ROOMSTATE: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn followers_only_is_off_at_minus_one() {
+        assert_eq!(update("followers-only=-1").followers_only, Some(None));
+    }
+
+    #[test]
+    fn followers_only_zero_allows_any_follower() {
+        assert_eq!(
+            update("followers-only=0").followers_only,
+            Some(Some(Duration::ZERO))
+        );
+        assert_eq!(
+            update("followers-only=10").followers_only,
+            Some(Some(Duration::from_secs(600)))
+        );
+    }
+
+    #[test]
+    fn slow_is_off_at_zero() {
+        assert_eq!(update("slow=0").slow, Some(None));
+        assert_eq!(update("slow=30").slow, Some(Some(Duration::from_secs(30))));
+    }
+
+    #[test]
+    fn partial_update_leaves_other_settings_unchanged() {
+        let mut state = RoomState::default();
+        state.apply(&update(
+            "emote-only=0;followers-only=-1;r9k=1;room-id=1337;slow=10;subs-only=1",
+        ));
+        state.apply(&update("room-id=1337;emote-only=1"));
+
+        assert_eq!(
+            state,
+            RoomState {
+                room_id: Some("1337".to_string()),
+                emote_only: true,
+                followers_only: None,
+                unique_chat: true,
+                slow: Some(Duration::from_secs(10)),
+                subs_only: true,
+            }
+        );
+    }
+}
@@ -0,0 +1,223 @@
+/// The set of IRCv3 message tags attached to a message.
+///
+/// Tag values are unescaped when parsed, and tags sent without a value are stored with an empty
+/// value (the IRCv3 specification treats the two as equivalent).
+///
+/// ## Example
+/// ```rust
+/// # use consolation::irc::Tags;
+/// let tags = Tags::parse("id=abc;tmi-sent-ts=1694600000000;mod=1;system-msg=hi\\sthere;+flag");
+///
+/// assert_eq!(tags.get("system-msg"), Some("hi there"));
+/// assert_eq!(tags.get_u64("tmi-sent-ts"), Some(1694600000000));
+/// assert_eq!(tags.get_bool("mod"), Some(true));
+/// assert_eq!(tags.get("+flag"), Some(""));
+/// assert!(Tags::is_client_only("+flag"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags(Vec<(String, String)>);
+
+impl Tags {
+    /// Parses the tags section of a raw IRC message, without the leading `@`.
+    ///
+    /// If a key appears more than once, the last value wins.
+    pub fn parse(input: &str) -> Self {
+        let mut tags = Self::default();
+
+        for tag in input.split(';').filter(|tag| !tag.is_empty()) {
+            let (key, value) = tag.split_once('=').unwrap_or((tag, ""));
+            if key.is_empty() {
+                continue;
+            }
+
+            tags.insert(key, unescape(value));
+        }
+
+        tags
+    }
+
+    /// Sets the value of a tag, replacing any existing value for the same key.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        let value = value.into();
+
+        match self.0.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.0.push((key, value)),
+        }
+    }
+
+    /// Returns the unescaped value of a tag, or `None` if the tag is not present.
+    ///
+    /// Tags without a value yield `Some("")`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the value of a tag, treating an empty value the same as a missing tag.
+    ///
+    /// Twitch sends many tags (such as `color` or `emotes`) with an empty value when they do not
+    /// apply.
+    pub fn get_non_empty(&self, key: &str) -> Option<&str> {
+        self.get(key).filter(|v| !v.is_empty())
+    }
+
+    /// Returns the value of a tag parsed as a `u64`, or `None` if it is missing or not a valid
+    /// unsigned integer.
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        self.get(key)?.parse().ok()
+    }
+
+    /// Returns the value of a tag parsed as an `i64`, or `None` if it is missing or not a valid
+    /// integer.
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.get(key)?.parse().ok()
+    }
+
+    /// Returns the value of a boolean tag, or `None` if it is missing or not a boolean.
+    ///
+    /// Both `1`/`0` (as used by Twitch) and `true`/`false` are accepted.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            "1" | "true" => Some(true),
+            "0" | "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the tag is present, with or without a value.
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns an iterator over every tag as a `(key, value)` pair, in the order received.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns an iterator over the client-only tags (those whose key starts with `+`).
+    pub fn client_only(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.iter().filter(|(k, _)| Self::is_client_only(k))
+    }
+
+    /// Returns `true` if `key` names a client-only tag, i.e. one prefixed with `+`.
+    pub fn is_client_only(key: &str) -> bool {
+        key.starts_with('+')
+    }
+
+    /// Returns the number of tags.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if there are no tags.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Escapes a value for use in the tags of an outgoing message.
+///
+/// This is the inverse of [`unescape`].
+pub(crate) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            ';' => escaped.push_str("\\:"),
+            ' ' => escaped.push_str("\\s"),
+            '\\' => escaped.push_str("\\\\"),
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Unescapes an IRCv3 tag value.
+///
+/// `\:`, `\s`, `\\`, `\r` and `\n` are replaced with `;`, a space, `\`, CR and LF respectively.
+/// Any other escaped character is kept as-is without the backslash, and a trailing lone backslash
+/// is dropped.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some(':') => unescaped.push(';'),
+            Some('s') => unescaped.push(' '),
+            Some('\\') => unescaped.push('\\'),
+            Some('r') => unescaped.push('\r'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescapes_every_escape_sequence() {
+        assert_eq!(unescape("a\\:b"), "a;b");
+        assert_eq!(unescape("a\\sb"), "a b");
+        assert_eq!(unescape("a\\\\b"), "a\\b");
+        assert_eq!(unescape("a\\rb"), "a\rb");
+        assert_eq!(unescape("a\\nb"), "a\nb");
+        assert_eq!(unescape("\\\\s"), "\\s");
+    }
+
+    #[test]
+    fn unknown_escapes_drop_the_backslash() {
+        assert_eq!(unescape("a\\bc"), "abc");
+        assert_eq!(unescape("\\日"), "日");
+    }
+
+    #[test]
+    fn trailing_lone_backslash_is_dropped() {
+        assert_eq!(unescape("abc\\"), "abc");
+        assert_eq!(unescape("\\"), "");
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        let value = "a;b c\\d\re\nf日";
+
+        assert_eq!(unescape(&escape(value)), value);
+    }
+
+    #[test]
+    fn empty_value_and_missing_value_are_equivalent() {
+        let tags = Tags::parse("key=;bare;other=x");
+
+        assert_eq!(tags.get("key"), Some(""));
+        assert_eq!(tags.get("bare"), Some(""));
+        assert!(tags.contains("bare"));
+        assert_eq!(tags.get_non_empty("key"), None);
+        assert_eq!(tags.get_non_empty("bare"), None);
+        assert_eq!(tags.get("missing"), None);
+        assert_eq!(tags.len(), 3);
+    }
+
+    #[test]
+    fn last_duplicate_key_wins_and_empty_keys_are_skipped() {
+        let tags = Tags::parse("a=1;;=x;a=2");
+
+        assert_eq!(tags.iter().collect::<Vec<_>>(), [("a", "2")]);
+    }
+}
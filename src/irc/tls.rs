@@ -0,0 +1,116 @@
+use std::{io, net::TcpStream, sync::Arc};
+
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, ServerName},
+    ClientConfig, ClientConnection, RootCertStore, StreamOwned,
+};
+
+use super::transport::Transport;
+use crate::{Error, Result};
+
+/// TLS settings for an IRC connection, passed to [`IrcBuilder::with_tls`].
+///
+/// By default, server certificates are verified against the Mozilla root certificates bundled
+/// with the `webpki-roots` crate.
+///
+/// ## Example
+/// ```rust,no_run
+/// # use consolation::irc::{IrcBuilder, TlsConfig};
+/// # fn main() -> consolation::Result<()> {
+/// let irc = IrcBuilder::default()
+///     .with_tls(TlsConfig::new("irc.chat.twitch.tv")?)
+///     .connect("irc.chat.twitch.tv:6697")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`IrcBuilder::with_tls`]: super::IrcBuilder::with_tls
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    server_name: ServerName<'static>,
+    roots: RootCertStore,
+}
+
+impl TlsConfig {
+    /// Creates a TLS configuration which verifies that the server's certificate is valid for
+    /// `server_name`, trusting the bundled Mozilla root certificates.
+    pub fn new(server_name: impl Into<String>) -> Result<Self> {
+        let mut config = Self::without_roots(server_name)?;
+        config
+            .roots
+            .extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+        Ok(config)
+    }
+
+    /// Creates a TLS configuration for `server_name` which trusts no root certificates at all.
+    ///
+    /// At least one root certificate must be added with [`TlsConfig::with_root_certificate_pem`]
+    /// or [`TlsConfig::with_root_certificate_der`] before connecting.
+    pub fn without_roots(server_name: impl Into<String>) -> Result<Self> {
+        let server_name = ServerName::try_from(server_name.into())
+            .map_err(|e| Error::InvalidInput(format!("invalid server name: {}", e)))?;
+
+        Ok(Self {
+            server_name,
+            roots: RootCertStore::empty(),
+        })
+    }
+
+    /// Trusts every certificate in a PEM-encoded bundle as a root, e.g. a custom or self-signed
+    /// certificate authority.
+    pub fn with_root_certificate_pem(mut self, pem: &[u8]) -> Result<Self> {
+        let mut found = false;
+        for certificate in CertificateDer::pem_slice_iter(pem) {
+            let certificate = certificate
+                .map_err(|e| Error::InvalidInput(format!("invalid PEM certificate: {}", e)))?;
+            self.add_root(certificate)?;
+            found = true;
+        }
+
+        if !found {
+            return Err(Error::InvalidInput(
+                "no certificates found in PEM input".into(),
+            ));
+        }
+
+        Ok(self)
+    }
+
+    /// Trusts a DER-encoded certificate as a root, e.g. a custom or self-signed certificate
+    /// authority.
+    pub fn with_root_certificate_der(mut self, der: impl Into<Vec<u8>>) -> Result<Self> {
+        self.add_root(CertificateDer::from(der.into()))?;
+
+        Ok(self)
+    }
+
+    fn add_root(&mut self, certificate: CertificateDer<'static>) -> Result<()> {
+        self.roots
+            .add(certificate)
+            .map_err(|e| Error::InvalidInput(format!("invalid root certificate: {}", e)))
+    }
+
+    /// Performs the TLS handshake over an established TCP connection, whose read and write
+    /// timeouts must be set.
+    pub(crate) fn wrap(&self, mut tcp: TcpStream) -> Result<Transport> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| Error::InvalidInput(e.to_string()))?
+            .with_root_certificates(self.roots.clone())
+            .with_no_client_auth();
+
+        let mut conn = ClientConnection::new(Arc::new(config), self.server_name.clone())
+            .map_err(|e| Error::InvalidInput(e.to_string()))?;
+        while conn.is_handshaking() {
+            // The socket's read and write timeouts bound each step of the handshake.
+            conn.complete_io(&mut tcp).map_err(|e| match e.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => Error::RegistrationTimeout,
+                _ => e.into(),
+            })?;
+        }
+
+        Ok(Transport::Tls(Box::new(StreamOwned::new(conn, tcp))))
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+};
+
+/// The byte stream underlying an IRC connection, either plaintext or TLS.
+#[derive(Debug)]
+pub(crate) enum Transport {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Transport {
+    /// Writes a single line, appending the trailing CRLF.
+    pub(crate) fn send_line(&mut self, line: &str) -> io::Result<()> {
+        self.write_all(format!("{}\r\n", line).as_bytes())?;
+        self.flush()
+    }
+
+    /// Returns the TCP socket underneath the transport, e.g. to configure timeouts.
+    pub(crate) fn tcp(&self) -> &TcpStream {
+        match self {
+            Self::Plain(stream) => stream,
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.get_ref(),
+        }
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => match stream.read(buf) {
+                // Servers commonly close the socket without sending a TLS `close_notify` alert,
+                // which is treated as a regular end of stream.
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(0),
+                result => result,
+            },
+        }
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.flush(),
+        }
+    }
+}
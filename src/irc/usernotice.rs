@@ -0,0 +1,320 @@
+use super::Tags;
+
+/// Represents a Twitch `USERNOTICE`: an event such as a subscription, a raid or an announcement,
+/// which may carry a chat message from the user who caused it.
+///
+/// These are only sent by the server if the `twitch.tv/commands` and `twitch.tv/tags`
+/// capabilities were requested.
+#[derive(Debug, Clone)]
+pub struct UserNotice {
+    /// The channel the event happened in, including the leading `#`.
+    pub channel: String,
+
+    /// The login name of the user who caused the event, e.g. the subscriber or the raider.
+    pub login: String,
+
+    /// The display name of the user who caused the event, falling back to their login name.
+    pub display_name: String,
+
+    /// What happened.
+    pub kind: UserNoticeKind,
+
+    /// The chat message the user chose to share along with the event, if any.
+    pub message: Option<String>,
+
+    /// Twitch's own description of the event, e.g. `ronni has subscribed for 6 months!`.
+    pub system_message: String,
+
+    /// The IRCv3 tags sent with the event, including every `msg-param-*` tag.
+    pub tags: Tags,
+}
+
+/// The kind of event a [`UserNotice`] represents, determined by its `msg-id` tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserNoticeKind {
+    /// A user subscribed for the first time.
+    Sub {
+        /// The subscription plan.
+        plan: SubPlan,
+    },
+
+    /// A user renewed their subscription.
+    Resub {
+        /// The total number of months the user has been subscribed.
+        cumulative_months: u32,
+
+        /// The number of consecutive months the user has been subscribed, if they chose to
+        /// share it.
+        streak_months: Option<u32>,
+
+        /// The subscription plan.
+        plan: SubPlan,
+    },
+
+    /// A user gifted a subscription to another user.
+    SubGift {
+        /// The login name of the user receiving the subscription.
+        recipient_login: String,
+
+        /// The display name of the user receiving the subscription.
+        recipient_display_name: String,
+
+        /// The subscription plan.
+        plan: SubPlan,
+    },
+
+    /// A user gifted subscriptions to several random users at once. The individual gifts follow
+    /// as [`UserNoticeKind::SubGift`] events.
+    SubMysteryGift {
+        /// The number of subscriptions gifted.
+        count: u32,
+
+        /// The subscription plan.
+        plan: SubPlan,
+    },
+
+    /// A user continued a subscription they were gifted.
+    GiftPaidUpgrade {
+        /// The login name of the user who gifted the original subscription, or `None` if it was
+        /// gifted anonymously.
+        gifter_login: Option<String>,
+    },
+
+    /// A user converted their Prime Gaming subscription into a paid one.
+    PrimePaidUpgrade {
+        /// The new subscription plan.
+        plan: SubPlan,
+    },
+
+    /// A user raided the channel.
+    Raid {
+        /// The number of viewers who came along with the raid.
+        viewer_count: u32,
+    },
+
+    /// A raid was cancelled.
+    Unraid,
+
+    /// A user earned a new Bits badge tier.
+    BitsBadgeTier {
+        /// The number of Bits the tier represents.
+        threshold: u32,
+    },
+
+    /// A moderator or the broadcaster highlighted a message as an announcement.
+    Announcement {
+        /// The highlight color chosen, such as `PRIMARY`, `BLUE`, `GREEN`, `ORANGE` or `PURPLE`.
+        color: String,
+    },
+
+    /// A ritual happened, such as a new chatter saying hello for the first time.
+    Ritual {
+        /// The name of the ritual, e.g. `new_chatter`.
+        name: String,
+    },
+
+    /// Any other event, or a known one whose parameters are missing. Contains the `msg-id` tag,
+    /// which is empty if the tag was not sent.
+    Other(String),
+}
+
+impl UserNoticeKind {
+    /// Determines the kind of event from the tags of a `USERNOTICE`.
+    pub(crate) fn from_tags(tags: &Tags) -> Self {
+        let msg_id = tags.get("msg-id").unwrap_or_default();
+
+        Self::from_msg_id(msg_id, tags).unwrap_or_else(|| Self::Other(msg_id.to_string()))
+    }
+
+    fn from_msg_id(msg_id: &str, tags: &Tags) -> Option<Self> {
+        let param = |name: &str| tags.get_non_empty(&format!("msg-param-{}", name));
+        let number = |name: &str| param(name)?.parse::<u32>().ok();
+        let plan = || param("sub-plan").map(SubPlan::parse);
+
+        let kind = match msg_id {
+            "sub" => Self::Sub { plan: plan()? },
+            "resub" => Self::Resub {
+                cumulative_months: number("cumulative-months")?,
+                streak_months: match tags.get_bool("msg-param-should-share-streak") {
+                    Some(true) => number("streak-months"),
+                    _ => None,
+                },
+                plan: plan()?,
+            },
+            "subgift" => Self::SubGift {
+                recipient_login: param("recipient-user-name")?.to_string(),
+                recipient_display_name: param("recipient-display-name")
+                    .or(param("recipient-user-name"))?
+                    .to_string(),
+                plan: plan()?,
+            },
+            "submysterygift" => Self::SubMysteryGift {
+                count: number("mass-gift-count")?,
+                plan: plan()?,
+            },
+            "giftpaidupgrade" => Self::GiftPaidUpgrade {
+                gifter_login: Some(param("sender-login")?.to_string()),
+            },
+            "anongiftpaidupgrade" => Self::GiftPaidUpgrade { gifter_login: None },
+            "primepaidupgrade" => Self::PrimePaidUpgrade { plan: plan()? },
+            "raid" => Self::Raid {
+                viewer_count: number("viewerCount")?,
+            },
+            "unraid" => Self::Unraid,
+            "bitsbadgetier" => Self::BitsBadgeTier {
+                threshold: number("threshold")?,
+            },
+            "announcement" => Self::Announcement {
+                color: param("color").unwrap_or("PRIMARY").to_string(),
+            },
+            "ritual" => Self::Ritual {
+                name: param("ritual-name")?.to_string(),
+            },
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+}
+
+/// A Twitch subscription plan, as sent in the `msg-param-sub-plan` tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubPlan {
+    /// A subscription included with Prime Gaming.
+    Prime,
+
+    /// A tier 1 subscription.
+    Tier1,
+
+    /// A tier 2 subscription.
+    Tier2,
+
+    /// A tier 3 subscription.
+    Tier3,
+
+    /// A plan not known to this crate, as sent by the server.
+    Other(String),
+}
+
+impl SubPlan {
+    fn parse(plan: &str) -> Self {
+        match plan {
+            "Prime" => Self::Prime,
+            "1000" => Self::Tier1,
+            "2000" => Self::Tier2,
+            "3000" => Self::Tier3,
+            _ => Self::Other(plan.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irc::Message;
+
+    fn kind(tags: &str) -> UserNoticeKind {
+        let line = format!("@{} :tmi.twitch.tv USERNOTICE #dallas", tags);
+        match Message::parse(&line) {
+            Ok(Message::UserNotice(notice)) => notice.kind,
+            other => panic!("not parsed as a USERNOTICE: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resub_months_are_read() {
+        assert_eq!(
+            kind(
+                "msg-id=resub;msg-param-cumulative-months=12;msg-param-should-share-streak=1;\
+                 msg-param-streak-months=3;msg-param-sub-plan=2000"
+            ),
+            UserNoticeKind::Resub {
+                cumulative_months: 12,
+                streak_months: Some(3),
+                plan: SubPlan::Tier2,
+            }
+        );
+    }
+
+    #[test]
+    fn resub_streak_is_hidden_unless_shared() {
+        assert_eq!(
+            kind(
+                "msg-id=resub;msg-param-cumulative-months=12;msg-param-should-share-streak=0;\
+                 msg-param-streak-months=3;msg-param-sub-plan=Prime"
+            ),
+            UserNoticeKind::Resub {
+                cumulative_months: 12,
+                streak_months: None,
+                plan: SubPlan::Prime,
+            }
+        );
+    }
+
+    #[test]
+    fn subgift_recipient_is_read() {
+        assert_eq!(
+            kind(
+                "msg-id=subgift;msg-param-recipient-user-name=fefe;\
+                 msg-param-recipient-display-name=FeFe;msg-param-sub-plan=1000"
+            ),
+            UserNoticeKind::SubGift {
+                recipient_login: "fefe".to_string(),
+                recipient_display_name: "FeFe".to_string(),
+                plan: SubPlan::Tier1,
+            }
+        );
+        assert_eq!(
+            kind("msg-id=subgift;msg-param-recipient-user-name=fefe;msg-param-sub-plan=1000"),
+            UserNoticeKind::SubGift {
+                recipient_login: "fefe".to_string(),
+                recipient_display_name: "fefe".to_string(),
+                plan: SubPlan::Tier1,
+            }
+        );
+    }
+
+    #[test]
+    fn raid_viewer_count_is_read() {
+        assert_eq!(
+            kind("msg-id=raid;msg-param-viewerCount=42"),
+            UserNoticeKind::Raid { viewer_count: 42 }
+        );
+    }
+
+    #[test]
+    fn announcement_color_defaults_to_primary() {
+        assert_eq!(
+            kind("msg-id=announcement;msg-param-color=BLUE"),
+            UserNoticeKind::Announcement {
+                color: "BLUE".to_string()
+            }
+        );
+        assert_eq!(
+            kind("msg-id=announcement"),
+            UserNoticeKind::Announcement {
+                color: "PRIMARY".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn missing_parameters_fall_back_to_other() {
+        for (tags, msg_id) in [
+            ("msg-id=resub;msg-param-sub-plan=1000", "resub"),
+            ("msg-id=subgift;msg-param-sub-plan=1000", "subgift"),
+            ("msg-id=raid;msg-param-viewerCount=", "raid"),
+            ("msg-id=raid;msg-param-viewerCount=many", "raid"),
+            ("msg-id=sub", "sub"),
+            ("msg-id=charitydonation", "charitydonation"),
+            ("login=ronni", ""),
+        ] {
+            assert_eq!(
+                kind(tags),
+                UserNoticeKind::Other(msg_id.to_string()),
+                "{}",
+                tags
+            );
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use super::{badges, Tags};
+
+/// Represents a Twitch `USERSTATE`: the logged-in user's details in a channel, sent after joining
+/// it and after each message sent to it.
+///
+/// The latest state of each channel is kept by [`Irc`](super::Irc) (see
+/// [`Irc::user_state`](super::Irc::user_state)).
+#[derive(Debug, Clone, Default)]
+pub struct UserState {
+    /// The channel the details apply to, including the leading `#`.
+    pub channel: String,
+
+    /// The user's display name, if sent.
+    pub display_name: Option<String>,
+
+    /// The user's chat color as a hex string such as `#0D4200`, or `None` if they never set one.
+    pub color: Option<String>,
+
+    /// The IDs of the emote sets the user can use.
+    pub emote_sets: Vec<String>,
+
+    /// Whether the user is a moderator in the channel.
+    pub is_moderator: bool,
+
+    /// Whether the user is a VIP in the channel.
+    pub is_vip: bool,
+
+    /// Whether the user is the channel's broadcaster.
+    pub is_broadcaster: bool,
+
+    /// The IRCv3 tags sent with the message, such as `badges` and `badge-info`.
+    pub tags: Tags,
+}
+
+impl UserState {
+    pub(crate) fn new(channel: String, tags: Tags) -> Self {
+        let badges = badges::parse(&tags);
+        let has_badge = |name: &str| badges.iter().any(|badge| badge.name == name);
+
+        Self {
+            channel,
+            display_name: tags.get_non_empty("display-name").map(str::to_string),
+            color: tags.get_non_empty("color").map(str::to_string),
+            emote_sets: emote_sets(&tags),
+            is_moderator: tags.get_bool("mod") == Some(true) || has_badge("moderator"),
+            is_vip: tags.contains("vip") || has_badge("vip"),
+            is_broadcaster: has_badge("broadcaster"),
+            tags,
+        }
+    }
+
+    /// Returns `true` if the user is a moderator, a VIP or the broadcaster, which raises the rate
+    /// limit for messages sent to the channel.
+    pub fn is_privileged(&self) -> bool {
+        self.is_moderator || self.is_vip || self.is_broadcaster
+    }
+}
+
+/// Represents a Twitch `GLOBALUSERSTATE`: the logged-in user's details, sent once after logging
+/// in.
+///
+/// It is kept by [`Irc`](super::Irc) (see
+/// [`Irc::global_user_state`](super::Irc::global_user_state)).
+#[derive(Debug, Clone, Default)]
+pub struct GlobalUserState {
+    /// The user's ID, if sent.
+    pub user_id: Option<String>,
+
+    /// The user's display name, if sent.
+    pub display_name: Option<String>,
+
+    /// The user's chat color as a hex string such as `#0D4200`, or `None` if they never set one.
+    pub color: Option<String>,
+
+    /// The IDs of the emote sets the user can use.
+    pub emote_sets: Vec<String>,
+
+    /// The IRCv3 tags sent with the message, such as `badges` and `user-type`.
+    pub tags: Tags,
+}
+
+impl GlobalUserState {
+    pub(crate) fn new(tags: Tags) -> Self {
+        Self {
+            user_id: tags.get_non_empty("user-id").map(str::to_string),
+            display_name: tags.get_non_empty("display-name").map(str::to_string),
+            color: tags.get_non_empty("color").map(str::to_string),
+            emote_sets: emote_sets(&tags),
+            tags,
+        }
+    }
+}
+
+/// Returns the emote set IDs in the `emote-sets` tag.
+fn emote_sets(tags: &Tags) -> Vec<String> {
+    tags.get("emote-sets")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|set| !set.is_empty())
+        .map(str::to_string)
+        .collect()
+}
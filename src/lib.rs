@@ -1 +1,4 @@
+mod error;
 pub mod irc;
+
+pub use error::{Error, ParseError, Result};
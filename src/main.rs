@@ -1,29 +1,74 @@
-use std::io;
-
 use consolation::irc::*;
 
-fn main() -> io::Result<()> {
+mod viewer;
+
+use viewer::Viewer;
+
+fn main() -> consolation::Result<()> {
     let args: Vec<_> = std::env::args().collect();
     if args.len() < 2 {
-        eprintln!("usage: {} <channel name>", args[0]);
+        eprintln!("usage: {} <channel name>...", args[0]);
 
         return Ok(());
     }
-    let channel_name = &args[1];
+    let channel_names = &args[1..];
 
-    let mut irc = IrcBuilder::default()
-        .with_nickname("meownadic")
-        .with_password(std::env::var("TWITCH_OAUTH_PASS").unwrap())
+    let builder = match std::env::var("TWITCH_OAUTH_PASS") {
+        Ok(password) => IrcBuilder::default()
+            .with_nickname("meownadic")
+            .with_password(password),
+        Err(_) => {
+            eprintln!("note: TWITCH_OAUTH_PASS is not set, connecting anonymously (read-only)");
+            IrcBuilder::anonymous()
+        }
+    };
+    let builder = builder
         .with_capability("twitch.tv/tags")
-        .connect("irc.chat.twitch.tv:6667")?;
+        .with_capability("twitch.tv/commands")
+        .with_lenient_parsing(true)
+        .with_reconnect(ReconnectPolicy::default());
+
+    #[cfg(feature = "tls")]
+    let mut irc = builder
+        .with_tls(TlsConfig::new("irc.chat.twitch.tv")?)
+        .connect("irc.chat.twitch.tv:6697")?;
+    #[cfg(not(feature = "tls"))]
+    let mut irc = builder.connect("irc.chat.twitch.tv:6667")?;
 
-    irc.join(channel_name)?;
+    if !irc.has_capability("twitch.tv/tags") {
+        eprintln!("warning: the server rejected twitch.tv/tags, message details will be missing");
+    }
+
+    irc.join_all(channel_names)?;
+    let mut viewer = Viewer::new(channel_names.len() > 1);
 
     while let Some(message) = irc.receive()? {
         match message {
-            Message::PrivMsg(PrivMsg { username, message }) => {
-                println!("{}: {}", username, message);
+            Message::PrivMsg(msg) => viewer.chat(&msg),
+            Message::UserNotice(notice) => viewer.user_notice(&notice),
+            Message::ClearChat(clear) => viewer.clear_chat(&clear),
+            Message::ClearMsg(clear) => viewer.clear_msg(&clear),
+            Message::Notice(notice) => viewer.notice(&notice),
+            Message::RoomState(update) => {
+                if let Some(state) = irc.room_state(&update.channel) {
+                    viewer.room_state(&update.channel, state);
+                }
+            }
+            Message::Malformed { error, .. } => {
+                eprintln!("skipping malformed line: {}", error);
+            }
+            Message::Disconnected { reason } => {
+                eprintln!("--- disconnected ({}), reconnecting ---", reason);
+            }
+            Message::Reconnected { .. } => {
+                eprintln!("--- reconnected, messages sent in the meantime were missed ---");
             }
+            Message::Join { .. }
+            | Message::Part { .. }
+            | Message::Names { .. }
+            | Message::UserState(_)
+            | Message::GlobalUserState(_)
+            | Message::Unknown(_) => {}
         }
     }
 
@@ -0,0 +1,499 @@
+use std::{
+    collections::VecDeque,
+    io::{self, IsTerminal, Write},
+    time::Duration,
+};
+
+use consolation::irc::*;
+use terminal_size::{Height, Width};
+
+mod color;
+
+use color::ColorDepth;
+
+/// How many printed lines are remembered so they can be redrawn.
+const HISTORY_LEN: usize = 200;
+
+/// How many characters of the message being replied to are shown above a reply.
+const REPLY_PREVIEW_LEN: usize = 60;
+
+/// The colors of the role glyphs, after Twitch's own badges.
+const BROADCASTER_COLOR: Rgb = Rgb::new(0xE9, 0x19, 0x16);
+const MODERATOR_COLOR: Rgb = Rgb::new(0x00, 0xAD, 0x03);
+const VIP_COLOR: Rgb = Rgb::new(0xE0, 0x05, 0xB9);
+const SUBSCRIBER_COLOR: Rgb = Rgb::new(0x83, 0x8C, 0xFF);
+
+/// The SGR attributes of bold and dim text.
+const BOLD: &str = "1";
+const DIM: &str = "2";
+
+/// The color of Twitch events such as subscriptions and raids.
+const EVENT_COLOR: Rgb = Rgb::new(0xBF, 0x94, 0xFF);
+
+/// The color of feedback from the server.
+const NOTICE_COLOR: Rgb = Rgb::new(0xFF, 0xD3, 0x3D);
+
+/// The color of the chat settings status line.
+const STATUS_COLOR: Rgb = Rgb::new(0x00, 0xC8, 0xC8);
+
+/// Renders chat events to the terminal.
+pub struct Viewer {
+    /// Whether lines are prefixed with their channel, because several channels are watched.
+    show_channel: bool,
+
+    /// Whether standard output is a terminal, which allows rewriting printed lines.
+    interactive: bool,
+
+    /// The terminal width and height set with the `COLUMNS` and `LINES` environment variables,
+    /// which override the size reported by the terminal.
+    size_override: Option<(usize, usize)>,
+
+    /// How many colors usernames, badges and system lines can be shown in.
+    color_depth: ColorDepth,
+
+    /// The most recently printed lines, oldest first.
+    history: VecDeque<Line>,
+}
+
+/// A line printed by the [`Viewer`], remembered so it can be redrawn.
+struct Line {
+    channel: String,
+
+    /// The login name of the user who sent the message, if it is a chat message.
+    login: Option<String>,
+
+    /// The `id` tag of the message, if any.
+    id: Option<String>,
+
+    /// The rendered line, including ANSI styling but without the trailing newline.
+    text: String,
+
+    /// Whether a moderator removed the message.
+    deleted: bool,
+
+    /// Whether this is a status line, which is replaced rather than repeated when it is the last
+    /// line printed.
+    status: bool,
+}
+
+impl Viewer {
+    pub fn new(show_channel: bool) -> Self {
+        let size = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|&value: &usize| value > 0)
+        };
+
+        Self {
+            show_channel,
+            interactive: io::stdout().is_terminal(),
+            size_override: size("COLUMNS").zip(size("LINES")),
+            color_depth: ColorDepth::detect(),
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn chat(&mut self, msg: &PrivMsg) {
+        if let Some(reply) = msg.reply_context() {
+            let text = format!(
+                "↳ replying to {}: {}",
+                reply.parent_display_name,
+                truncate(&reply.parent_body, REPLY_PREVIEW_LEN)
+            );
+            let text = self.style(&text, DIM, None);
+            self.system(&msg.channel, text);
+        }
+
+        let mut name = self.paint(&msg.display_name, msg.display_color());
+        if msg.has_localized_name() {
+            name.push_str(&format!(" ({})", msg.login));
+        }
+        // `/me` messages read as actions, e.g. "ronni waves".
+        let separator = if msg.is_action() { " " } else { ": " };
+        let mut text = format!("{}{}{}", self.badge_glyphs(msg), name, separator);
+        if let Some(bits) = msg.bits() {
+            let label = format!("[{} bits]", bits);
+            text = format!("{} {}", self.paint(&label, cheer_color(bits)), text);
+        }
+        for segment in msg.segments() {
+            match segment {
+                Segment::Text(part) => text.push_str(part),
+                Segment::Emote(emote) => text.push_str(&emote.text),
+                Segment::Cheermote(cheer) => {
+                    text.push_str(&self.paint(&cheer.text, cheer_color(cheer.amount)));
+                }
+            }
+        }
+
+        self.print(Line {
+            channel: msg.channel.clone(),
+            login: Some(msg.login.clone()),
+            id: msg.id().map(str::to_string),
+            text,
+            deleted: false,
+            status: false,
+        });
+    }
+
+    /// Prints a Twitch event as a highlighted system line, followed by the user's message if any.
+    pub fn user_notice(&mut self, notice: &UserNotice) {
+        if let (UserNoticeKind::Announcement { .. }, Some(message)) =
+            (&notice.kind, &notice.message)
+        {
+            let text = format!("[announcement] {}: {}", notice.display_name, message);
+            let text = self.style(&text, BOLD, None);
+            self.system(&notice.channel, text);
+            return;
+        }
+
+        if !notice.system_message.is_empty() {
+            let text = format!("*** {}", notice.system_message);
+            let text = self.style(&text, BOLD, Some(EVENT_COLOR));
+            self.system(&notice.channel, text);
+        }
+        if let Some(message) = &notice.message {
+            self.print(Line {
+                channel: notice.channel.clone(),
+                login: Some(notice.login.clone()),
+                id: notice.tags.get_non_empty("id").map(str::to_string),
+                text: self.paint(
+                    &format!("  ↳ {}: {}", notice.display_name, message),
+                    EVENT_COLOR,
+                ),
+                deleted: false,
+                status: false,
+            });
+        }
+    }
+
+    /// Marks the messages removed by a moderator as deleted, then reports the action.
+    pub fn clear_chat(&mut self, clear: &ClearChat) {
+        let login = clear.action.login();
+        self.delete(|line| {
+            line.channel == clear.channel
+                && line.login.is_some()
+                && (login.is_none() || line.login.as_deref() == login)
+        });
+
+        let text = match &clear.action {
+            ClearChatAction::ClearAll => "chat was cleared by a moderator".to_string(),
+            ClearChatAction::Timeout {
+                login, duration, ..
+            } => format!("{} was timed out for {}s", login, duration.as_secs()),
+            ClearChatAction::Ban { login, .. } => format!("{} was banned", login),
+        };
+        let text = self.style(&format!("--- {}", text), DIM, None);
+        self.system(&clear.channel, text);
+    }
+
+    /// Marks the message deleted by a moderator as deleted, or reports the deletion if it cannot
+    /// be redrawn.
+    pub fn clear_msg(&mut self, clear: &ClearMsg) {
+        let target_msg_id = clear.target_msg_id.as_ref();
+        if target_msg_id.is_some() && self.delete(|line| line.id.as_ref() == target_msg_id) {
+            return;
+        }
+
+        let login = clear.login.as_deref().unwrap_or("someone");
+        let text = format!("--- a message from {} was deleted", login);
+        let text = self.style(&text, DIM, None);
+        self.system(&clear.channel, text);
+    }
+
+    /// Prints feedback from the server as a system line.
+    pub fn notice(&mut self, notice: &Notice) {
+        let channel = notice.channel.as_deref().unwrap_or("*");
+        let text = self.paint(&format!("*** {}", notice.text), NOTICE_COLOR);
+        self.system(channel, text);
+    }
+
+    /// Shows the chat settings of a channel, replacing the previous status line if nothing was
+    /// printed since.
+    pub fn room_state(&mut self, channel: &str, state: &RoomState) {
+        let mut modes = Vec::new();
+        if let Some(duration) = state.slow {
+            modes.push(format!("slow {}", format_duration(duration)));
+        }
+        match state.followers_only {
+            Some(duration) if duration.is_zero() => modes.push("followers-only".to_string()),
+            Some(duration) => modes.push(format!("followers-only {}", format_duration(duration))),
+            None => {}
+        }
+        if state.subs_only {
+            modes.push("subs-only".to_string());
+        }
+        if state.emote_only {
+            modes.push("emote-only".to_string());
+        }
+        if state.unique_chat {
+            modes.push("unique-chat".to_string());
+        }
+        if modes.is_empty() {
+            modes.push("no chat restrictions".to_string());
+        }
+
+        let line = Line {
+            channel: channel.to_string(),
+            login: None,
+            id: None,
+            text: self.style(&format!("[{}]", modes.join(" · ")), DIM, Some(STATUS_COLOR)),
+            deleted: false,
+            status: true,
+        };
+
+        let replaces_last = self
+            .history
+            .back()
+            .is_some_and(|last| last.status && last.channel == line.channel);
+        let last_rows = self.history.back().and_then(|last| self.rows(last));
+        if let (true, true, Some(rows)) = (self.interactive, replaces_last, last_rows) {
+            self.history.pop_back();
+            print!("\x1b[{}A\r\x1b[J", rows);
+        }
+        self.print(line);
+    }
+
+    /// Returns `text` in the foreground `color`, as far as the terminal supports it.
+    fn paint(&self, text: &str, color: Rgb) -> String {
+        self.style(text, "", Some(color))
+    }
+
+    /// Returns `text` with the SGR `attributes`, such as [`BOLD`] or [`DIM`], and in the foreground
+    /// `color` as far as the terminal supports it, or unstyled if it supports no styling at all.
+    fn style(&self, text: &str, attributes: &str, color: Option<Rgb>) -> String {
+        if self.color_depth == ColorDepth::None {
+            return text.to_string();
+        }
+
+        let mut styled = String::new();
+        if !attributes.is_empty() {
+            styled.push_str(&format!("\x1b[{}m", attributes));
+        }
+        if let Some(color) = color {
+            styled.push_str(&self.color_depth.foreground(color));
+        }
+        format!("{}{}\x1b[0m", styled, text)
+    }
+
+    /// Returns compact glyphs for the sender's roles, such as `[M]` for a moderator or `[S12]`
+    /// for a subscriber of 12 months, followed by a space if there are any.
+    fn badge_glyphs(&self, msg: &PrivMsg) -> String {
+        let mut glyphs = String::new();
+        if msg.is_broadcaster() {
+            glyphs.push_str(&self.paint("[B]", BROADCASTER_COLOR));
+        }
+        if msg.is_moderator() {
+            glyphs.push_str(&self.paint("[M]", MODERATOR_COLOR));
+        }
+        if msg.is_vip() {
+            glyphs.push_str(&self.paint("[V]", VIP_COLOR));
+        }
+        if let Some(months) = msg.subscriber_months() {
+            glyphs.push_str(&self.paint(&format!("[S{}]", months), SUBSCRIBER_COLOR));
+        }
+
+        if !glyphs.is_empty() {
+            glyphs.push(' ');
+        }
+        glyphs
+    }
+
+    /// Prints a line which is not a chat message.
+    fn system(&mut self, channel: &str, text: String) {
+        self.print(Line {
+            channel: channel.to_string(),
+            login: None,
+            id: None,
+            text,
+            deleted: false,
+            status: false,
+        });
+    }
+
+    fn print(&mut self, line: Line) {
+        println!("{}", self.render(&line));
+
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(line);
+    }
+
+    /// Marks the remembered lines matching `predicate` as deleted and, if they are still on the
+    /// screen, redraws them struck through.
+    ///
+    /// Returns `false` if nothing could be redrawn, because no line matched, the output is not a
+    /// terminal, the lines have scrolled out of view or it is unknown how many rows they occupy.
+    fn delete(&mut self, predicate: impl Fn(&Line) -> bool) -> bool {
+        let Some(first) = self
+            .history
+            .iter()
+            .position(|line| !line.deleted && predicate(line))
+        else {
+            return false;
+        };
+
+        for line in self.history.iter_mut().skip(first) {
+            if predicate(line) {
+                line.deleted = true;
+            }
+        }
+
+        let rows: Option<usize> = self
+            .history
+            .iter()
+            .skip(first)
+            .map(|line| self.rows(line))
+            .sum();
+        let (Some(rows), Some((_, height))) = (rows, self.terminal_size()) else {
+            return false;
+        };
+        if !self.interactive || rows >= height {
+            return false;
+        }
+
+        // Move the cursor up to the first affected line, clear everything below it and print the
+        // lines again.
+        let mut out = io::stdout().lock();
+        let _ = write!(out, "\x1b[{}A\r\x1b[J", rows);
+        for line in self.history.iter().skip(first) {
+            let _ = writeln!(out, "{}", self.render(line));
+        }
+        let _ = out.flush();
+
+        true
+    }
+
+    /// Returns how a line is printed, including its channel prefix and strikethrough if deleted.
+    fn render(&self, line: &Line) -> String {
+        let prefix = if self.show_channel {
+            format!("[{}] ", line.channel)
+        } else {
+            String::new()
+        };
+
+        if line.deleted {
+            // Keep the strikethrough active across any style resets within the line.
+            let text = line.text.replace("\x1b[0m", "\x1b[0;9;2m");
+            format!("\x1b[9;2m{}{}\x1b[0m", prefix, text)
+        } else {
+            format!("{}{}", prefix, line.text)
+        }
+    }
+
+    /// Returns the terminal width and height, used to count how many rows a line occupies, or
+    /// `None` if standard output is not a terminal. Printed lines are only rewritten when it is
+    /// known.
+    ///
+    /// The size is read on each call, so that resizing the terminal is taken into account.
+    fn terminal_size(&self) -> Option<(usize, usize)> {
+        self.size_override.or_else(|| {
+            let (Width(columns), Height(rows)) = terminal_size::terminal_size_of(io::stdout())?;
+            Some((usize::from(columns), usize::from(rows)))
+        })
+    }
+
+    /// Returns how many terminal rows a line occupies once printed, or `None` if the terminal
+    /// size or the line's width is unknown.
+    fn rows(&self, line: &Line) -> Option<usize> {
+        let (columns, _) = self.terminal_size()?;
+        let width = visible_width(&self.render(line))?;
+
+        Some(width.div_ceil(columns).max(1))
+    }
+}
+
+/// Returns the color of the cheermote tier which `amount` Bits belong to.
+fn cheer_color(amount: u64) -> Rgb {
+    match amount {
+        100_000.. => Rgb::new(0xF3, 0xA7, 0x1A),
+        10_000.. => Rgb::new(0xF4, 0x30, 0x21),
+        5_000.. => Rgb::new(0x00, 0x99, 0xFE),
+        1_000.. => Rgb::new(0x1D, 0xB2, 0xA5),
+        100.. => Rgb::new(0x9C, 0x3E, 0xE8),
+        _ => Rgb::new(0x97, 0x97, 0x97),
+    }
+}
+
+/// Shortens `text` to at most `max_len` characters, ending it with `…` if anything was cut.
+fn truncate(text: &str, max_len: usize) -> String {
+    if text.chars().count() <= max_len {
+        return text.to_string();
+    }
+
+    let mut truncated: String = text.chars().take(max_len - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Formats a duration in the largest unit which divides it evenly, e.g. `30s`, `10m` or `1d`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    match seconds {
+        0 => "0s".to_string(),
+        _ if seconds.is_multiple_of(86_400) => format!("{}d", seconds / 86_400),
+        _ if seconds.is_multiple_of(3_600) => format!("{}h", seconds / 3_600),
+        _ if seconds.is_multiple_of(60) => format!("{}m", seconds / 60),
+        _ => format!("{}s", seconds),
+    }
+}
+
+/// Returns the number of terminal columns `text` occupies, not counting ANSI escape sequences, or
+/// `None` if it contains characters whose width depends on the terminal.
+fn visible_width(text: &str) -> Option<usize> {
+    let mut width = 0;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip to the final byte of the control sequence.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += char_width(c)?;
+        }
+    }
+
+    Some(width)
+}
+
+/// Returns the number of terminal columns `c` occupies: two for East Asian wide characters and
+/// most emoji, one for others, or `None` for characters which combine with their neighbors or
+/// are drawn differently by different terminals.
+fn char_width(c: char) -> Option<usize> {
+    match u32::from(c) {
+        // Combining marks, zero-width characters, variation selectors and flag letters.
+        0x0300..=0x036F
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+        | 0x1F1E6..=0x1F1FF
+        | 0x1F3FB..=0x1F3FF
+        | 0xE0000..=0xE007F => None,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F680..=0x1F6FF
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => Some(2),
+        // Other emoji and symbols are drawn narrow or wide depending on the terminal.
+        0x2600..=0x27BF | 0x1F000..=0x1F2FF | 0x1F700..=0x1F8FF | 0x1FA00..=0x1FAFF => None,
+        _ if c.is_control() => Some(0),
+        _ => Some(1),
+    }
+}
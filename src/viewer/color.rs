@@ -0,0 +1,152 @@
+use std::io::{self, IsTerminal};
+
+use consolation::irc::Rgb;
+
+/// The minimum relative luminance of a color printed on the (assumed dark) terminal background.
+const MIN_LUMINANCE: f64 = 0.2;
+
+/// The levels of each component in the 6×6×6 color cube of 256-color terminals.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The usual values of the 16 basic terminal colors, in the order of their SGR codes (30–37, then
+/// 90–97).
+const BASIC_COLORS: [Rgb; 16] = [
+    Rgb::new(0, 0, 0),
+    Rgb::new(205, 0, 0),
+    Rgb::new(0, 205, 0),
+    Rgb::new(205, 205, 0),
+    Rgb::new(0, 0, 238),
+    Rgb::new(205, 0, 205),
+    Rgb::new(0, 205, 205),
+    Rgb::new(229, 229, 229),
+    Rgb::new(127, 127, 127),
+    Rgb::new(255, 0, 0),
+    Rgb::new(0, 255, 0),
+    Rgb::new(255, 255, 0),
+    Rgb::new(92, 92, 255),
+    Rgb::new(255, 0, 255),
+    Rgb::new(0, 255, 255),
+    Rgb::new(255, 255, 255),
+];
+
+/// How many colors the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// 24-bit colors.
+    TrueColor,
+
+    /// The 256-color xterm palette.
+    Ansi256,
+
+    /// The 16 basic colors.
+    Ansi16,
+
+    /// No colors or other styling at all, because standard output is not a terminal or as
+    /// requested with the `NO_COLOR` environment variable.
+    None,
+}
+
+impl ColorDepth {
+    /// Guesses the terminal's capability from the `NO_COLOR`, `COLORTERM` and `TERM` environment
+    /// variables, or returns [`ColorDepth::None`] if standard output is not a terminal.
+    pub fn detect() -> Self {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+
+        if !io::stdout().is_terminal() || !var("NO_COLOR").is_empty() {
+            Self::None
+        } else if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+            Self::TrueColor
+        } else if var("TERM").contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+
+    /// Returns the escape sequence which sets the foreground to `color`, made readable on a dark
+    /// background and approximated to the terminal's palette.
+    pub fn foreground(self, color: Rgb) -> String {
+        let color = readable(color);
+
+        match self {
+            Self::TrueColor => format!("\x1b[38;2;{};{};{}m", color.r, color.g, color.b),
+            Self::Ansi256 => format!("\x1b[38;5;{}m", nearest_256(color)),
+            Self::Ansi16 => {
+                let index = nearest(color, BASIC_COLORS.iter().copied());
+                let code = if index < 8 {
+                    30 + index
+                } else {
+                    90 + index - 8
+                };
+                format!("\x1b[{}m", code)
+            }
+            Self::None => String::new(),
+        }
+    }
+}
+
+/// Lightens `color` by mixing it with white until it is bright enough to read on a dark
+/// background.
+fn readable(color: Rgb) -> Rgb {
+    let mix = |component: u8, amount: f64| {
+        (f64::from(component) + (255.0 - f64::from(component)) * amount).round() as u8
+    };
+
+    (0..=10)
+        .map(|step| {
+            let amount = f64::from(step) / 10.0;
+            Rgb::new(
+                mix(color.r, amount),
+                mix(color.g, amount),
+                mix(color.b, amount),
+            )
+        })
+        .find(|color| luminance(*color) >= MIN_LUMINANCE)
+        .unwrap_or(Rgb::new(255, 255, 255))
+}
+
+/// Returns the relative luminance of `color`, from 0 for black to 1 for white, as defined by
+/// WCAG.
+fn luminance(color: Rgb) -> f64 {
+    let linear = |component: u8| {
+        let c = f64::from(component) / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    0.2126 * linear(color.r) + 0.7152 * linear(color.g) + 0.0722 * linear(color.b)
+}
+
+/// Returns the index of the 256-color palette entry closest to `color`, among the color cube
+/// (16–231) and the grayscale ramp (232–255).
+fn nearest_256(color: Rgb) -> usize {
+    let cube = (0..216).map(|i| {
+        Rgb::new(
+            CUBE_LEVELS[i / 36],
+            CUBE_LEVELS[i / 6 % 6],
+            CUBE_LEVELS[i % 6],
+        )
+    });
+    let grays = (0..24).map(|i| {
+        let level = 8 + 10 * i as u8;
+        Rgb::new(level, level, level)
+    });
+
+    16 + nearest(color, cube.chain(grays))
+}
+
+/// Returns the index of the color in `palette` closest to `color`.
+fn nearest(color: Rgb, palette: impl Iterator<Item = Rgb>) -> usize {
+    let distance = |other: Rgb| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(color.r, other.r) + d(color.g, other.g) + d(color.b, other.b)
+    };
+
+    palette
+        .enumerate()
+        .min_by_key(|&(_, other)| distance(other))
+        .map_or(0, |(index, _)| index)
+}
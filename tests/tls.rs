@@ -0,0 +1,123 @@
+#![cfg(feature = "tls")]
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener},
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use consolation::{
+    irc::{IrcBuilder, Message, TlsConfig},
+    Error,
+};
+use rcgen::{CertifiedKey, KeyPair};
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    ServerConfig, ServerConnection, StreamOwned,
+};
+
+/// Generates a self-signed certificate for `localhost`.
+fn self_signed() -> CertifiedKey<KeyPair> {
+    rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+}
+
+/// Starts a TLS IRC server on a local port which registers one client, sends it a message once it
+/// joins a channel, and returns the lines it received.
+fn serve(certified: &CertifiedKey<KeyPair>) -> (SocketAddr, JoinHandle<Vec<String>>) {
+    let certificate = CertificateDer::from(certified.cert.der().to_vec());
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+        certified.signing_key.serialize_der(),
+    ));
+    let config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![certificate], key)
+            .unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let thread = thread::spawn(move || {
+        let (tcp, _) = listener.accept().unwrap();
+        let conn = ServerConnection::new(Arc::new(config)).unwrap();
+        let mut stream = BufReader::new(StreamOwned::new(conn, tcp));
+
+        let mut received = Vec::new();
+        let mut line = String::new();
+        while stream.read_line(&mut line).unwrap_or(0) > 0 {
+            let command = line.trim_end().to_string();
+            line.clear();
+
+            let reply = if command.starts_with("NICK") {
+                ":tmi.twitch.tv 001 tester :Welcome, GLHF!\r\n:tmi.twitch.tv 376 tester :>\r\n"
+            } else if command.starts_with("JOIN") {
+                ":ronni!ronni@ronni.tmi.twitch.tv PRIVMSG #dallas :over TLS\r\n"
+            } else {
+                ""
+            };
+            received.push(command);
+
+            let stream = stream.get_mut();
+            stream.write_all(reply.as_bytes()).unwrap();
+            if reply.contains("PRIVMSG") {
+                stream.conn.send_close_notify();
+                stream.flush().unwrap();
+                break;
+            }
+        }
+
+        received
+    });
+
+    (addr, thread)
+}
+
+#[test]
+fn connects_to_server_with_trusted_self_signed_certificate() {
+    let certified = self_signed();
+    let (addr, server) = serve(&certified);
+
+    let tls = TlsConfig::without_roots("localhost")
+        .unwrap()
+        .with_root_certificate_der(certified.cert.der().to_vec())
+        .unwrap();
+    let mut irc = IrcBuilder::default()
+        .with_nickname("tester")
+        .with_password("oauth:secret")
+        .with_tls(tls)
+        .connect(addr)
+        .unwrap();
+    assert_eq!(irc.server_info().welcome, "Welcome, GLHF!");
+
+    irc.join("dallas").unwrap();
+    let Some(Message::PrivMsg(msg)) = irc.receive().unwrap() else {
+        panic!("expected a PRIVMSG");
+    };
+    assert_eq!(msg.channel, "#dallas");
+    assert_eq!(msg.message, "over TLS");
+    assert!(irc.receive().unwrap().is_none());
+
+    assert_eq!(
+        server.join().unwrap(),
+        ["PASS oauth:secret", "NICK tester", "JOIN #dallas"]
+    );
+}
+
+#[test]
+fn rejects_untrusted_certificate() {
+    let (addr, _server) = serve(&self_signed());
+
+    let tls = TlsConfig::without_roots("localhost")
+        .unwrap()
+        .with_root_certificate_der(self_signed().cert.der().to_vec())
+        .unwrap();
+    let result = IrcBuilder::default()
+        .with_nickname("tester")
+        .with_tls(tls)
+        .connect(addr);
+
+    assert!(matches!(result, Err(Error::Transport(_))));
+}